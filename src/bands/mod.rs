@@ -0,0 +1,62 @@
+use anyhow::{anyhow, Result};
+
+use crate::models::{
+    cli::Cli,
+    units::{Capacity, Unit},
+};
+
+/// VM size bands, the limits are the GiB boundaries between the bands
+#[derive(Debug, Clone)]
+pub struct SizeBands {
+    limits: Vec<f64>,
+    large_block_gib: f64,
+}
+
+impl SizeBands {
+    /// Size bands from the CLI, `None` when the VMs aren't split
+    pub fn from_cli(cli: &Cli) -> Result<Option<Self>> {
+        if !cli.size_bands {
+            return Ok(None);
+        }
+
+        let mut limits = cli.band_limits.clone();
+
+        if limits.is_empty() || limits.iter().any(|x| !x.is_finite() || *x <= 0.0) {
+            return Err(anyhow!(
+                "Band limits must be one or more sizes in GiB above 0"
+            ));
+        }
+
+        limits.sort_by(f64::total_cmp);
+        limits.dedup();
+
+        Ok(Some(Self {
+            limits,
+            large_block_gib: cli.large_block_gib,
+        }))
+    }
+
+    /// Index of the band a VM falls in
+    pub fn band(&self, capacity: Capacity) -> usize {
+        let gib = capacity.to(Unit::Gib);
+        self.limits.iter().take_while(|x| gib >= **x).count()
+    }
+
+    /// Band name used in the workload names, e.g. 500-2048GiB
+    pub fn label(&self, band: usize) -> String {
+        match (
+            band.checked_sub(1).map(|x| self.limits[x]),
+            self.limits.get(band),
+        ) {
+            (None, Some(upper)) => format!("lt{}GiB", upper),
+            (Some(lower), Some(upper)) => format!("{}-{}GiB", lower, upper),
+            (Some(lower), None) => format!("gt{}GiB", lower),
+            (None, None) => "all".to_string(),
+        }
+    }
+
+    /// Bands starting at or above the large block size use VSE large block handling
+    pub fn is_large(&self, band: usize) -> bool {
+        band > 0 && self.limits[band - 1] >= self.large_block_gib
+    }
+}
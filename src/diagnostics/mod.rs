@@ -0,0 +1,60 @@
+use comfy_table::{
+    modifiers::{UTF8_ROUND_CORNERS, UTF8_SOLID_INNER_BORDERS},
+    presets::UTF8_FULL,
+    Table,
+};
+use itertools::Itertools;
+
+use crate::models::errors::Diagnostic;
+
+/// Rows listed per warning group, the rest are counted
+const ROWS_SHOWN: usize = 5;
+
+/// Prints the warnings grouped by file, sheet, column and problem
+pub fn print_summary(warnings: &[Diagnostic]) {
+    if warnings.is_empty() {
+        return;
+    }
+
+    let mut table = Table::new();
+
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(vec!["File", "Sheet", "Column", "Problem", "Count", "Rows"]);
+
+    warnings
+        .iter()
+        .into_group_map_by(|x| (&x.file, &x.sheet, &x.column, &x.message))
+        .into_iter()
+        .sorted_by(|a, b| a.0.cmp(&b.0))
+        .for_each(|((file, sheet, column, message), group)| {
+            let mut rows = group
+                .iter()
+                .filter_map(|x| x.row)
+                .take(ROWS_SHOWN)
+                .map(|x| x.to_string())
+                .join(", ");
+            if group.len() > ROWS_SHOWN && !rows.is_empty() {
+                rows.push_str(", ...");
+            }
+
+            let problem = match group.first().and_then(|x| x.value.as_ref()) {
+                Some(value) => format!("{} - e.g. {}", message, value),
+                None => message.to_string(),
+            };
+
+            table.add_row(vec![
+                file.to_string(),
+                sheet.clone().unwrap_or_default(),
+                column.clone().unwrap_or_default(),
+                problem,
+                group.len().to_string(),
+                rows,
+            ]);
+        });
+
+    println!("Warnings - skipped while loading, use --strict to fail instead:");
+    println!("{table}");
+}
@@ -1,208 +1,639 @@
-use crate::{helpers, models::errors::MyError};
-use calamine::{open_workbook, Reader, Xlsx};
-use helpers::{ColPosition, GetFloat, GetString};
-use fastmurmur3;
+use crate::{
+    helpers,
+    models::errors::{Diagnostic, MyError},
+};
+use calamine::{DataType, Range, Reader, Xlsx, XlsxError};
+use helpers::{
+    find_column, version_from_headers, ColPosition, Column, GetDate, GetFloat, GetString, IsTrue,
+    RvtoolsVersion, SheetRef,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read, Seek};
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
 
 use crate::models::{
     cli::Cli,
-    rvtools::{Vinfo, Vpartition},
+    rvtools::{RvtoolsData, Vinfo, VmKind, Vpartition, Vsnapshot},
+    units::{Capacity, Unit},
 };
 
-pub fn get_excel(cli: &Cli) -> Result<(Vec<Vinfo>, Vec<Vpartition>), MyError> {
-    let mut excel_vec: Vec<Xlsx<_>> = Vec::new();
+/// Any reader calamine can open a workbook from
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+pub fn get_excel(cli: &Cli) -> Result<RvtoolsData, MyError> {
+    let sources = cli
+        .rvtools_files
+        .iter()
+        .map(|file| open_source(file))
+        .collect::<Result<Vec<_>, MyError>>()?;
+
+    get_excel_from(sources, cli)
+}
+
+/// Opens an RVTools file, "-" reads the workbook from stdin into memory as stdin can't seek
+pub fn open_source(file: &Path) -> Result<(String, Box<dyn ReadSeek>), MyError> {
+    if file == Path::new("-") {
+        let mut buffer = Vec::new();
+        io::stdin()
+            .read_to_end(&mut buffer)
+            .map_err(XlsxError::Io)?;
+        Ok(("stdin".to_string(), Box::new(Cursor::new(buffer))))
+    } else {
+        let reader = BufReader::new(File::open(file).map_err(XlsxError::Io)?);
+        Ok((file.display().to_string(), Box::new(reader)))
+    }
+}
 
-    if cli.rvtools_files.len() == 0 {
+/// Reads the RVTools workbooks from any readers, e.g. a `Cursor` over an xlsx held in memory.
+/// Each source is a name for the messages and the xlsx data.
+///
+/// The workbooks are read in parallel, `cli.jobs` at a time, and each workbook is dropped
+/// once its rows are read.
+pub fn get_excel_from<RS: Read + Seek + Send>(
+    sources: Vec<(String, RS)>,
+    cli: &Cli,
+) -> Result<RvtoolsData, MyError> {
+    if sources.is_empty() {
         return Err(MyError::RvtoolsError(
             "No RVTools file or files specified".to_string(),
         ));
     }
 
-    for file in &cli.rvtools_files {
-        let new_excel: Xlsx<_> = open_workbook(file)?;
-        excel_vec.push(new_excel);
-    }
+    let total = sources.len();
+    let jobs = cli
+        .jobs
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |x| x.get()))
+        .clamp(1, total);
+
+    let queue = Mutex::new(sources.into_iter().enumerate());
+    let results = Mutex::new(Vec::with_capacity(total));
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let Some((i, (name, reader))) = queue.lock().unwrap().next() else {
+                    break;
+                };
+
+                let start = Instant::now();
+                let result = read_workbook(&name, reader, cli);
+
+                let mut results = results.lock().unwrap();
+
+                match &result {
+                    Ok(data) => eprintln!(
+                        "[{}/{}] {}: {} vInfo, {} vPartition and {} vSnapshot rows in {:.2?}",
+                        results.len() + 1,
+                        total,
+                        name,
+                        data.info.len(),
+                        data.partitions.len(),
+                        data.snapshots.len(),
+                        start.elapsed()
+                    ),
+                    Err(e) if !cli.strict => {
+                        eprintln!(
+                            "[{}/{}] {}: skipped - {}",
+                            results.len() + 1,
+                            total,
+                            name,
+                            e
+                        )
+                    }
+                    Err(_) => {}
+                }
+
+                results.push((i, name, result));
+            });
+        }
+    });
 
     let mut info_vec: Vec<Vinfo> = Vec::new();
     let mut part_vec: Vec<Vpartition> = Vec::new();
+    let mut snap_vec: Vec<Vsnapshot> = Vec::new();
+    let mut excluded: BTreeMap<VmKind, usize> = BTreeMap::new();
+    let mut warnings: Vec<Diagnostic> = Vec::new();
+    let mut errors: Vec<MyError> = Vec::new();
+
+    // Merged in the file order so the output doesn't depend on the thread timing
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|x| x.0);
+
+    for (_, name, result) in results {
+        let data = match result {
+            Ok(data) => data,
+            Err(e) => {
+                errors.push(e.in_file(&name));
+                continue;
+            }
+        };
+
+        info_vec.extend(data.info);
+        part_vec.extend(data.partitions);
+        snap_vec.extend(data.snapshots);
+        warnings.extend(data.warnings);
+        data.excluded
+            .into_iter()
+            .for_each(|(kind, count)| *excluded.entry(kind).or_insert(0) += count);
+    }
 
-    for (i, mut excel) in excel_vec.into_iter().enumerate() {
-        let workbook = excel.worksheet_range("vInfo");
+    // Lenient mode skips the files that can't be read, as long as one of them can
+    if !errors.is_empty() && (cli.strict || errors.len() == total) {
+        return Err(errors.remove(0));
+    }
+
+    warnings.extend(errors.into_iter().map(|e| Diagnostic::new("", None, e)));
+
+    let vm_keys = info_vec
+        .iter()
+        .map(|x| (x.source.as_str(), x.vm_name.as_str()))
+        .collect::<HashSet<_>>();
+
+    let snap_vec = snap_vec
+        .into_iter()
+        .filter(|x| vm_keys.contains(&(x.source.as_str(), x.vm_name.as_str())))
+        .collect::<Vec<Vsnapshot>>();
+
+    Ok(RvtoolsData {
+        info: info_vec,
+        partitions: part_vec,
+        snapshots: snap_vec,
+        excluded,
+        warnings,
+    })
+}
+
+/// Collects the problems skipped over in lenient mode
+struct Warnings<'a> {
+    file: &'a str,
+    strict: bool,
+    list: Vec<Diagnostic>,
+}
+
+impl Warnings<'_> {
+    /// The value, or None with a warning when the error can be skipped in lenient mode
+    fn skip<T>(&mut self, sheet: &str, result: Result<T, MyError>) -> Result<Option<T>, MyError> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if self.strict => Err(e),
+            Err(e) => {
+                self.list.push(Diagnostic::new(self.file, Some(sheet), e));
+                Ok(None)
+            }
+        }
+    }
+}
 
-        if workbook.is_none() {
-            return Err(MyError::VinfoError("vInfo sheet not found".to_string()));
+/// Reads the vInfo, vPartition and vSnapshot rows of one workbook
+fn read_workbook<RS: Read + Seek>(
+    name: &str,
+    reader: RS,
+    cli: &Cli,
+) -> Result<RvtoolsData, MyError> {
+    let mut excel: Xlsx<RS> = Xlsx::new(reader)?;
+
+    let mut warnings = Warnings {
+        file: name,
+        strict: cli.strict,
+        list: vec![],
+    };
+
+    let workbook = match excel.worksheet_range("vInfo") {
+        Some(workbook) => workbook?,
+        None => {
+            return Err(MyError::MissingSheet {
+                sheet: "vInfo".to_string(),
+            })
         }
+    };
 
-        let workbook = workbook.unwrap().unwrap();
+    let version = if cli.legacy {
+        Some(RvtoolsVersion(4, 1, 1))
+    } else {
+        detect_version(&mut excel).or_else(|| version_from_headers(&workbook))
+    };
 
-        let vm_column = workbook.get_col_pos(&"VM".to_string())?;
+    let (mut info_vec, excluded) = read_info(&workbook, version, cli, &mut warnings)?;
 
-        let power_column = workbook.get_col_pos(&"Powerstate".to_string())?;
+    if let Some(dc_include) = &cli.dc_include {
+        info_vec = info_vec
+            .into_iter()
+            .filter(|x| dc_include.contains(&x.datacenter))
+            .collect::<Vec<Vinfo>>();
+    }
+    if let Some(cluster_include) = &cli.cluster_include {
+        info_vec = info_vec
+            .into_iter()
+            .filter(|x| cluster_include.contains(&x.cluster))
+            .collect::<Vec<Vinfo>>();
+    }
+    if let Some(dc_exclude) = &cli.dc_exclude {
+        info_vec = info_vec
+            .into_iter()
+            .filter(|x| !dc_exclude.contains(&x.datacenter))
+            .collect::<Vec<Vinfo>>();
+    }
+    if let Some(cluster_exclude) = &cli.cluster_exclude {
+        info_vec = info_vec
+            .into_iter()
+            .filter(|x| !cluster_exclude.contains(&x.cluster))
+            .collect::<Vec<Vinfo>>();
+    }
+    if let Some(vm_exclude) = &cli.vm_exclude {
+        info_vec = info_vec
+            .into_iter()
+            .filter(|x| !vm_exclude.contains(&x.vm_name))
+            .collect::<Vec<Vinfo>>();
+    }
 
-        let cap_string = if cli.legacy {
-            "In Use MB"
-        } else {
-            "In Use MiB"
-        };
+    // An unreadable vPartition or vSnapshot sheet is skipped in lenient mode, the vInfo
+    // capacity is used instead
+    let part_vec = match excel.worksheet_range("vPartition") {
+        // vPartition capacity is not wanted, nothing to read
+        _ if cli.do_not_use_vpartition => vec![],
+        Some(partition) => {
+            let result = partition
+                .map_err(MyError::from)
+                .and_then(|x| read_partitions(&x, version, cli, &mut warnings));
+            warnings.skip("vPartition", result)?.unwrap_or_default()
+        }
+        None => {
+            println!(
+                "vPartition sheet not found in {:?}, continuing without it.",
+                name
+            );
+            vec![]
+        }
+    };
+
+    let snap_vec = match excel.worksheet_range("vSnapshot") {
+        Some(snapshot) => {
+            let result = snapshot
+                .map_err(MyError::from)
+                .and_then(|x| read_snapshots(&x, version, cli, &mut warnings));
+            warnings.skip("vSnapshot", result)?.unwrap_or_default()
+        }
+        None => {
+            if cli.snapshot_report || cli.subtract_snapshots || cli.snapshot_age_days.is_some() {
+                println!(
+                    "vSnapshot sheet not found in {:?}, continuing without it.",
+                    name
+                );
+            }
+            vec![]
+        }
+    };
+
+    Ok(RvtoolsData {
+        info: info_vec,
+        partitions: part_vec,
+        snapshots: snap_vec,
+        excluded,
+        warnings: warnings.list,
+    })
+}
 
-        let cap_column = workbook.get_col_pos(&cap_string.to_string())?;
+/// --group-by value for an empty cell of any column other than Datacenter and Cluster
+const EMPTY_GROUP_VALUE: &str = "Empty";
 
-        let dc_column = workbook.get_col_pos(&"Datacenter".to_string())?;
+/// Name for a missing datacenter or cluster from its template, e.g. "{dc}-standalone"
+fn fill_name(template: &str, dc: &str, host: Option<&str>) -> String {
+    template
+        .replace("{dc}", dc)
+        .replace("{host}", host.unwrap_or("unknown-host"))
+}
 
-        let cluster_column = workbook.get_col_pos(&"Cluster".to_string())?;
+/// The vInfo rows kept, and the count of the rows excluded by classification
+fn read_info(
+    workbook: &Range<DataType>,
+    version: Option<RvtoolsVersion>,
+    cli: &Cli,
+    warnings: &mut Warnings,
+) -> Result<(Vec<Vinfo>, BTreeMap<VmKind, usize>), MyError> {
+    let sheet = SheetRef {
+        file: warnings.file,
+        sheet: "vInfo",
+    };
 
-        for row in workbook.rows().enumerate().skip(1) {
-            let power_state = &row.1[power_column]
-                .get_string_value("vInfo - column Powerstate vInfo".to_string(), row.0 + 1)?;
+    let mut info_vec: Vec<Vinfo> = Vec::new();
+    let mut excluded: BTreeMap<VmKind, usize> = BTreeMap::new();
 
-            if power_state.contains("poweredOff") && !cli.include_powered_off {
-                continue;
-            }
+    let (vm_column, vm_string) = workbook.get_column(Column::Vm, version)?;
 
-            let mut vm_name =
-                &row.1[vm_column].get_string_value("vInfo - column 'VM'".to_string(), row.0 + 1)?;
+    let (power_column, power_string) = workbook.get_column(Column::Powerstate, version)?;
 
-            let anonymized_vm_name = anon_data(&vm_name, &cli);
-            vm_name = &anonymized_vm_name;
+    let (cap_column, cap_string) = workbook.get_column(Column::InUse, version)?;
 
-            let cap_error_string = if cli.legacy {
-                "vInfo - column 'Capacity MB'"
-            } else {
-                "vInfo - column 'Capacity MiB'"
-            };
+    let (dc_column, dc_string) = workbook.get_column(Column::Datacenter, version)?;
 
-            let cap = &row.1[cap_column]
-                .get_float_value(cap_error_string.to_string(), row.0 + 1)?;
+    let (cluster_column, cluster_string) = workbook.get_column(Column::Cluster, version)?;
 
-            let mut dc = &row.1[dc_column]
-                .get_string_value("vInfo - column 'Datacenter'".to_string(), row.0 + 1)?;
+    // Only read for the fallback names of VMs outside a cluster
+    let host_column =
+        if cli.standalone_cluster.contains("{host}") || cli.missing_datacenter.contains("{host}") {
+            Some(workbook.get_column(Column::Host, version)?)
+        } else {
+            None
+        };
 
-            let mut anan_dc = dc.clone();
-            if cli.anonymize {
-                let hash = fastmurmur3::hash(dc.as_bytes());
-                anan_dc = hash.to_string();
-            };
-            dc = &anan_dc;
+    // Older RVTools versions don't have these columns, the rules are skipped
+    let template_column = workbook.get_column(Column::Template, version).ok();
 
-            let mut cluster = &row.1[cluster_column]
-                .get_string_value("vInfo - column 'Cluster'".to_string(), row.0 + 1)?;
+    let srm_column = workbook.get_column(Column::SrmPlaceholder, version).ok();
 
-            let mut anon_cluster = cluster.clone();
-            if cli.anonymize { 
-                let hash = fastmurmur3::hash(cluster.as_bytes());
-                anon_cluster = hash.to_string();
-            }
-            cluster = &anon_cluster;
-
-            info_vec.push(Vinfo {
-                vm_name: vm_name.to_string(),
-                datacenter: dc.to_string(),
-                cluster: cluster.to_string(),
-                capacity: *cap,
-                powerstate: power_state.to_string(),
+    let group_columns = match &cli.group_by {
+        Some(group_by) => group_by
+            .iter()
+            .map(|x| {
+                let (column, depth) = parse_group_column(x);
+                Ok((column, workbook.get_col_pos(column)?, depth))
             })
-        }
-        if let Some(dc_include) = &cli.dc_include {
-            info_vec = info_vec
-                .into_iter()
-                .filter(|x| dc_include.contains(&x.datacenter))
-                .collect::<Vec<Vinfo>>();
-        }
-        if let Some(cluster_include) = &cli.cluster_include {
-            info_vec = info_vec
-                .into_iter()
-                .filter(|x| cluster_include.contains(&x.cluster))
-                .collect::<Vec<Vinfo>>();
-        }
-        if let Some(dc_exclude) = &cli.dc_exclude {
-            info_vec = info_vec
-                .into_iter()
-                .filter(|x| !dc_exclude.contains(&x.datacenter))
-                .collect::<Vec<Vinfo>>();
-        }
-        if let Some(cluster_exclude) = &cli.cluster_exclude {
-            info_vec = info_vec
-                .into_iter()
-                .filter(|x| !cluster_exclude.contains(&x.cluster))
-                .collect::<Vec<Vinfo>>();
-        }
-        if let Some(vm_exclude) = &cli.vm_exclude {
-            info_vec = info_vec
-                .into_iter()
-                .filter(|x| !vm_exclude.contains(&x.vm_name))
-                .collect::<Vec<Vinfo>>();
-        }
+            .collect::<Result<Vec<_>, MyError>>()?,
+        None => vec![],
+    };
 
-        let partition = excel.worksheet_range("vPartition");
+    for (index, row) in workbook.rows().enumerate().skip(1) {
+        let at = |column| sheet.at(index + 1, column);
 
-        if let Some(partition) = partition {
-            if partition.is_err() || cli.do_not_use_vpartition {
-                if !cli.do_not_use_vpartition {
-                    Err(MyError::VpartitionError(
-                        "vPartition sheet not found".to_string(),
-                    ))?;
-                }
-            } else {
-                let partition = partition.unwrap();
+        let Some(mut vm_name) =
+            warnings.skip("vInfo", row[vm_column].get_string_value(at(vm_string)))?
+        else {
+            continue;
+        };
 
-                let part_vm_column = partition.get_col_pos(&"VM".to_string())?;
+        let kind = if template_column.is_some_and(|(x, _)| row[x].is_true()) {
+            VmKind::Template
+        } else if srm_column.is_some_and(|(x, _)| row[x].is_true()) {
+            VmKind::SrmPlaceholder
+        } else if vm_name.ends_with(&cli.replica_suffix) {
+            VmKind::Replica
+        } else {
+            VmKind::Vm
+        };
 
-                let part_power_column = partition.get_col_pos(&"Powerstate".to_string())?;
+        let kind_included = match kind {
+            VmKind::Vm => true,
+            VmKind::Template => cli.include_templates,
+            VmKind::SrmPlaceholder => cli.include_srm_placeholders,
+            VmKind::Replica => cli.include_replicas,
+        };
 
-                let consumed_string = if cli.legacy {
-                    "Consumed MB"
-                } else {
-                    "Consumed MiB"
-                };
+        if !kind_included {
+            *excluded.entry(kind).or_insert(0) += 1;
+            continue;
+        }
 
-                let part_cap_column = partition.get_col_pos(&consumed_string.to_string())?;
-                for row in partition.rows().enumerate().skip(1) {
-                    let power_state = &row.1[part_power_column].get_string_value(
-                        "vParition - column 'Powerstate'".to_string(),
-                        row.0 + 1,
-                    )?;
+        let Some(power_state) = warnings.skip(
+            "vInfo",
+            row[power_column].get_optional_string(at(power_string)),
+        )?
+        else {
+            continue;
+        };
+        let power_state = power_state.unwrap_or_default();
 
-                    if power_state.contains("poweredOff") && !cli.include_powered_off {
-                        continue;
-                    }
+        if power_state.contains("poweredOff") && !cli.include_powered_off {
+            continue;
+        }
+
+        vm_name = anon_data(&vm_name, cli);
+
+        let Some(cap) = warnings.skip(
+            "vInfo",
+            row[cap_column].get_float_value(at(cap_string), cli.decimal_separator),
+        )?
+        else {
+            continue;
+        };
+        let cap = Capacity::new(cap, Unit::from_header(cap_string));
+
+        let Some((dc, cluster, host)) = warnings.skip(
+            "vInfo",
+            row[dc_column]
+                .get_optional_string(at(dc_string))
+                .and_then(|dc| {
+                    Ok((
+                        dc,
+                        row[cluster_column].get_optional_string(at(cluster_string))?,
+                        match host_column {
+                            Some((column, name)) => row[column].get_optional_string(at(name))?,
+                            None => None,
+                        },
+                    ))
+                }),
+        )?
+        else {
+            continue;
+        };
 
-                    let vm_name = &row.1[part_vm_column]
-                        .get_string_value("vParition - column 'VM'".to_string(), row.0 + 1)?;
+        let dc = dc.unwrap_or_else(|| fill_name(&cli.missing_datacenter, "", host.as_deref()));
+        let cluster =
+            cluster.unwrap_or_else(|| fill_name(&cli.standalone_cluster, &dc, host.as_deref()));
 
-                    let cap_error_string = if cli.legacy {
-                        "vParition - column 'Capacity MB'"
+        let group = if group_columns.is_empty() {
+            None
+        } else {
+            let values = group_columns
+                .iter()
+                .map(|(name, column, depth)| {
+                    // the Datacenter and Cluster columns get the same fallbacks as the VM's
+                    // own datacenter and cluster, other empty cells are grouped together
+                    let value = if *column == dc_column {
+                        dc.to_string()
+                    } else if *column == cluster_column {
+                        cluster.to_string()
                     } else {
-                        "vParition - column 'Capacity MiB'"
+                        row[*column]
+                            .get_optional_string(sheet.at(index + 1, name))?
+                            .unwrap_or_else(|| EMPTY_GROUP_VALUE.to_string())
+                    };
+                    let value = match depth {
+                        Some(depth) => folder_prefix(&value, *depth),
+                        None => value,
                     };
+                    Ok(anon_data(&value, cli))
+                })
+                .collect::<Result<Vec<_>, MyError>>();
 
-                    let cap = &row.1[part_cap_column].get_float_value(
-                        cap_error_string.to_string(),
-                        row.0 + 1,
-                    )?;
+            let Some(values) = warnings.skip("vInfo", values)? else {
+                continue;
+            };
+            Some(values.join("_"))
+        };
 
-                    part_vec.push(Vpartition {
-                        vm_name: vm_name.to_string(),
-                        capacity: *cap,
-                    })
-                }
-            }
-        } else {
-            if !cli.do_not_use_vpartition {
-                println!(
-                    "vPartition sheet not found in {:?}, continuing without it.",
-                    cli.rvtools_files[i]
-                );
-            }
+        info_vec.push(Vinfo {
+            vm_name,
+            datacenter: anon_data(&dc, cli),
+            cluster: anon_data(&cluster, cli),
+            capacity: cap,
+            powerstate: power_state,
+            kind,
+            group,
+            source: anon_data(warnings.file, cli),
+        })
+    }
+
+    Ok((info_vec, excluded))
+}
+
+fn read_partitions(
+    partition: &Range<DataType>,
+    version: Option<RvtoolsVersion>,
+    cli: &Cli,
+    warnings: &mut Warnings,
+) -> Result<Vec<Vpartition>, MyError> {
+    let sheet = SheetRef {
+        file: warnings.file,
+        sheet: "vPartition",
+    };
+
+    let mut part_vec: Vec<Vpartition> = Vec::new();
+
+    let (part_vm_column, vm_string) = partition.get_column(Column::Vm, version)?;
+
+    let (part_power_column, power_string) = partition.get_column(Column::Powerstate, version)?;
+
+    let (part_cap_column, consumed_string) = partition.get_column(Column::Consumed, version)?;
+
+    for (index, row) in partition.rows().enumerate().skip(1) {
+        let at = |column| sheet.at(index + 1, column);
+
+        let Some(power_state) = warnings.skip(
+            "vPartition",
+            row[part_power_column].get_optional_string(at(power_string)),
+        )?
+        else {
+            continue;
+        };
+        let power_state = power_state.unwrap_or_default();
+
+        if power_state.contains("poweredOff") && !cli.include_powered_off {
+            continue;
         }
+
+        let Some(vm_name) = warnings.skip(
+            "vPartition",
+            row[part_vm_column].get_string_value(at(vm_string)),
+        )?
+        else {
+            continue;
+        };
+
+        let Some(cap) = warnings.skip(
+            "vPartition",
+            row[part_cap_column].get_float_value(at(consumed_string), cli.decimal_separator),
+        )?
+        else {
+            continue;
+        };
+
+        part_vec.push(Vpartition {
+            vm_name,
+            capacity: Capacity::new(cap, Unit::from_header(consumed_string)),
+        })
+    }
+
+    Ok(part_vec)
+}
+
+fn read_snapshots(
+    snapshot: &Range<DataType>,
+    version: Option<RvtoolsVersion>,
+    cli: &Cli,
+    warnings: &mut Warnings,
+) -> Result<Vec<Vsnapshot>, MyError> {
+    let sheet = SheetRef {
+        file: warnings.file,
+        sheet: "vSnapshot",
+    };
+
+    let mut snap_vec: Vec<Vsnapshot> = Vec::new();
+
+    let (snap_vm_column, vm_string) = snapshot.get_column(Column::Vm, version)?;
+
+    let (snap_name_column, name_string) = snapshot.get_column(Column::SnapshotName, version)?;
+
+    let (snap_date_column, date_string) = snapshot.get_column(Column::SnapshotDate, version)?;
+
+    let (snap_size_column, snap_size_string) =
+        snapshot.get_column(Column::SnapshotSize, version)?;
+
+    for (index, row) in snapshot.rows().enumerate().skip(1) {
+        let at = |column| sheet.at(index + 1, column);
+
+        let values = row[snap_vm_column]
+            .get_string_value(at(vm_string))
+            .and_then(|vm_name| {
+                Ok((
+                    vm_name,
+                    row[snap_name_column]
+                        .get_optional_string(at(name_string))?
+                        .unwrap_or_default(),
+                    row[snap_date_column].get_date_value(at(date_string))?,
+                    row[snap_size_column]
+                        .get_float_value(at(snap_size_string), cli.decimal_separator)?,
+                ))
+            });
+
+        let Some((vm_name, name, date, size)) = warnings.skip("vSnapshot", values)? else {
+            continue;
+        };
+
+        snap_vec.push(Vsnapshot {
+            vm_name: anon_data(&vm_name, cli),
+            source: anon_data(warnings.file, cli),
+            name,
+            date,
+            capacity: Capacity::new(size, Unit::from_header(snap_size_string)),
+        })
     }
 
-    Ok((info_vec, part_vec))
+    Ok(snap_vec)
 }
 
-fn anon_data(item: &String, cli: &Cli) -> String {
-    let mut anon_item = item.clone();
+fn anon_data(item: &str, cli: &Cli) -> String {
+    let mut anon_item = item.to_string();
     if cli.anonymize {
         let hash = fastmurmur3::hash(anon_item.as_bytes());
         anon_item = hash.to_string();
     }
     anon_item
 }
+
+/// RVTools version from the vMetaData sheet, if the sheet is present
+fn detect_version<RS: Read + Seek>(excel: &mut Xlsx<RS>) -> Option<RvtoolsVersion> {
+    let meta = excel.worksheet_range("vMetaData")?.ok()?;
+    let column = find_column(&meta, &["RVTools version"])?;
+
+    match meta.rows().nth(1)?.get(column)? {
+        DataType::String(x) => RvtoolsVersion::parse(x),
+        DataType::Float(x) => RvtoolsVersion::parse(&x.to_string()),
+        DataType::Int(x) => RvtoolsVersion::parse(&x.to_string()),
+        _ => None,
+    }
+}
+
+/// Splits a group by item into the column name and an optional path depth, e.g. Folder:2
+fn parse_group_column(item: &str) -> (&str, Option<usize>) {
+    if let Some((column, depth)) = item.rsplit_once(':') {
+        if let Ok(depth) = depth.trim().parse::<usize>() {
+            return (column.trim(), Some(depth));
+        }
+    }
+    (item.trim(), None)
+}
+
+/// The first `depth` elements of a folder path
+fn folder_prefix(path: &str, depth: usize) -> String {
+    path.split('/')
+        .filter(|x| !x.is_empty())
+        .take(depth)
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use comfy_table::{
+    modifiers::{UTF8_ROUND_CORNERS, UTF8_SOLID_INNER_BORDERS},
+    presets::UTF8_FULL,
+    Table,
+};
+use serde::Serialize;
+
+use crate::models::{
+    cli::Cli,
+    new_model::DataProperty,
+    rvtools::Datacenter,
+    units::{Capacity, Unit},
+};
+
+/// Annual growth rates (%) per site, a site rate overrides the global rate
+#[derive(Debug, Clone)]
+pub struct GrowthPolicy {
+    pub default: f64,
+    pub sites: HashMap<String, f64>,
+    pub project_length: i64,
+}
+
+impl GrowthPolicy {
+    pub fn from_cli(cli: &Cli) -> Result<Self> {
+        let mut sites = HashMap::new();
+
+        if let Some(items) = &cli.site_growth {
+            for item in items {
+                let (site, rate) = item
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Site growth must be SITE=PERCENT, got {}", item))?;
+                sites.insert(site.trim().to_string(), rate.trim().parse::<f64>()?);
+            }
+        }
+
+        if cli.project_length < 1 {
+            return Err(anyhow!("Project length must be at least 1 year"));
+        }
+
+        Ok(Self {
+            default: cli.growth_rate,
+            sites,
+            project_length: cli.project_length,
+        })
+    }
+
+    /// Adds measured site rates, rates given on the CLI are kept
+    pub fn add_measured(&mut self, measured: HashMap<String, f64>) {
+        measured.into_iter().for_each(|(site, rate)| {
+            self.sites.entry(site).or_insert(rate);
+        });
+    }
+
+    pub fn rate_for(&self, site: &str) -> f64 {
+        self.sites.get(site).copied().unwrap_or(self.default)
+    }
+
+    /// VSE data property for a site, the global rate uses the default "dpopt" property
+    pub fn data_property_id(&self, site: &str) -> String {
+        let rate = self.rate_for(site).round() as i64;
+        if rate == self.default.round() as i64 {
+            "dpopt".to_string()
+        } else {
+            format!("dpopt_g{}", rate)
+        }
+    }
+
+    /// Data properties for all the rates in use, the global rate first
+    pub fn data_properties(&self, sites: &[String]) -> Vec<DataProperty> {
+        let mut properties = vec![data_property(
+            "dpopt".to_string(),
+            self.default.round() as i64,
+            true,
+        )];
+
+        sites.iter().for_each(|site| {
+            let id = self.data_property_id(site);
+            if !properties.iter().any(|x| x.data_property_id == id) {
+                properties.push(data_property(id, self.rate_for(site).round() as i64, false));
+            }
+        });
+
+        properties
+    }
+}
+
+fn data_property(id: String, growth: i64, default: bool) -> DataProperty {
+    let name = if default {
+        "Generic Optimistic".to_string()
+    } else {
+        format!("Generic Optimistic ({}% growth)", growth)
+    };
+
+    DataProperty::new(id, name, 5, 50, growth, default)
+}
+
+/// Annual growth per site between a baseline and the current capacity
+pub fn measure(
+    baseline: &HashMap<String, Capacity>,
+    current: &HashMap<String, Capacity>,
+    age_days: f64,
+) -> HashMap<String, f64> {
+    current
+        .iter()
+        .filter_map(|(site, cap)| {
+            let base = baseline.get(site)?.to(Unit::Mib);
+            if base <= 0.0 || age_days <= 0.0 {
+                return None;
+            }
+            let rate = ((cap.to(Unit::Mib) / base).powf(365.0 / age_days) - 1.0) * 100.0;
+            Some((site.to_string(), rate))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ForecastRow {
+    pub site: String,
+    pub cluster: String,
+    pub growth_rate: f64,
+    /// Source capacity for the current year then each project year, in the output unit
+    pub years: Vec<f64>,
+}
+
+pub fn forecast(datacenters: &[Datacenter], growth: &GrowthPolicy, unit: Unit) -> Vec<ForecastRow> {
+    datacenters
+        .iter()
+        .map(|x| {
+            let rate = growth.rate_for(&x.name);
+            ForecastRow {
+                site: x.name.to_string(),
+                cluster: x.cluster.to_string(),
+                growth_rate: rate,
+                years: (0..=growth.project_length)
+                    .map(|year| x.capacity.to(unit) * (1.0 + rate / 100.0).powi(year as i32))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+pub fn print_forecast(rows: &[ForecastRow], unit: Unit) {
+    let mut table = Table::new();
+
+    let year_count = rows.iter().map(|x| x.years.len()).max().unwrap_or(0);
+
+    let mut header = vec![
+        "Site".to_string(),
+        "Cluster".to_string(),
+        "Growth (%)".to_string(),
+        format!("Now ({})", unit),
+    ];
+    header.extend((1..year_count).map(|x| format!("Year {} ({})", x, unit)));
+
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(header);
+
+    rows.iter().for_each(|x| {
+        let mut row = vec![
+            x.site.to_string(),
+            x.cluster.to_string(),
+            format!("{:.1}", x.growth_rate),
+        ];
+        row.extend(x.years.iter().map(|y| format!("{:.2}", y)));
+        table.add_row(row);
+    });
+
+    println!("{table}");
+}
@@ -0,0 +1,194 @@
+use calamine::DataType;
+
+use crate::models::units::DecimalSeparator;
+
+use super::civil_date;
+
+/// Text of a cell, whole numbers are written without a fraction so a VM named 1234 that
+/// Excel stored as a number reads as "1234"
+pub fn to_text(cell: &DataType) -> Option<String> {
+    match cell {
+        DataType::String(t) => Some(t.to_string()),
+        DataType::Int(t) => Some(t.to_string()),
+        DataType::Float(t) if t.fract() == 0.0 && t.abs() < 1e15 => Some(format!("{}", *t as i64)),
+        DataType::Float(t) => Some(t.to_string()),
+        DataType::Bool(t) => Some(if *t { "True" } else { "False" }.to_string()),
+        DataType::DateTime(t) => {
+            let (year, month, day) = civil_date(*t);
+            let minutes = (t.fract() * 1440.0).round() as i64;
+
+            Some(if minutes == 0 {
+                format!("{:04}-{:02}-{:02}", year, month, day)
+            } else {
+                format!(
+                    "{:04}-{:02}-{:02} {:02}:{:02}",
+                    year,
+                    month,
+                    day,
+                    minutes / 60,
+                    minutes % 60
+                )
+            })
+        }
+        DataType::Error(_) | DataType::Empty => None,
+    }
+}
+
+/// Number in a cell, blank cells read as 0 and text is parsed with `parse_number`
+pub fn to_number(cell: &DataType, decimal: DecimalSeparator) -> Option<f64> {
+    match cell {
+        DataType::Float(t) | DataType::DateTime(t) => Some(*t),
+        DataType::Int(t) => Some(*t as f64),
+        DataType::Bool(t) => Some(if *t { 1.0 } else { 0.0 }),
+        DataType::String(t) if t.trim().is_empty() => Some(0.0),
+        DataType::String(t) => parse_number(t, decimal),
+        DataType::Empty => Some(0.0),
+        DataType::Error(_) => None,
+    }
+}
+
+/// RVTools writes booleans either as Excel booleans or as "True"/"False" text
+pub fn to_bool(cell: &DataType) -> bool {
+    match cell {
+        DataType::Bool(t) => *t,
+        DataType::String(t) => ["true", "yes", "1"]
+            .iter()
+            .any(|x| t.trim().eq_ignore_ascii_case(x)),
+        DataType::Int(t) => *t != 0,
+        DataType::Float(t) => *t != 0.0,
+        _ => false,
+    }
+}
+
+/// Excel serial date of a cell, text is read as YYYY-MM-DD, YYYY/MM/DD, DD.MM.YYYY or a serial
+pub fn to_date(cell: &DataType) -> Option<f64> {
+    match cell {
+        DataType::DateTime(t) | DataType::Float(t) => Some(*t),
+        DataType::Int(t) => Some(*t as f64),
+        DataType::String(t) => {
+            if let Some(serial) = parse_number(t, DecimalSeparator::Auto) {
+                return Some(serial);
+            }
+
+            let date = t.split([' ', 'T']).next().unwrap_or_default();
+            let parts = date
+                .split(['/', '-', '.'])
+                .map(|x| x.parse::<i64>())
+                .collect::<Result<Vec<_>, _>>()
+                .ok()?;
+
+            match parts[..] {
+                [y, m, d] if y > 1899 && !date.contains('.') => Some(super::excel_serial(y, m, d)),
+                [d, m, y] if y > 1899 && date.contains('.') => Some(super::excel_serial(y, m, d)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Parses numbers written as text, e.g. "1,024.5", "1.024,5", "1 024,5" or "1'024.5"
+///
+/// Digit groups after a thousands separator must have 3 digits, so "1,2,3" is not a number.
+pub fn parse_number(text: &str, decimal: DecimalSeparator) -> Option<f64> {
+    let text: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '\'')
+        .collect();
+
+    let decimal = match decimal {
+        DecimalSeparator::Dot => '.',
+        DecimalSeparator::Comma => ',',
+        DecimalSeparator::Auto => match (text.rfind('.'), text.rfind(',')) {
+            (Some(dot), Some(comma)) if comma > dot => ',',
+            (None, Some(comma)) if text.matches(',').count() == 1 && text.len() - comma != 4 => ',',
+            // "1.234.567" can only be dots as thousands separators
+            (Some(_), None) if text.matches('.').count() > 1 => ',',
+            _ => '.',
+        },
+    };
+    let thousands = if decimal == '.' { ',' } else { '.' };
+
+    let (integer, fraction) = match text.rsplit_once(decimal) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (text.as_str(), None),
+    };
+
+    let mut groups = integer.split(thousands);
+    let mut digits = groups.next()?.to_string();
+    for group in groups {
+        if group.len() != 3 {
+            return None;
+        }
+        digits.push_str(group);
+    }
+
+    if let Some(fraction) = fraction {
+        digits.push('.');
+        digits.push_str(fraction);
+    }
+
+    digits.parse::<f64>().ok().filter(|x| x.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auto(text: &str) -> Option<f64> {
+        parse_number(text, DecimalSeparator::Auto)
+    }
+
+    #[test]
+    fn auto_reads_the_documented_formats() {
+        assert_eq!(auto("1,024.5"), Some(1024.5));
+        assert_eq!(auto("1.024,5"), Some(1024.5));
+        assert_eq!(auto("1 024,5"), Some(1024.5));
+        assert_eq!(auto("1'024.5"), Some(1024.5));
+        assert_eq!(auto("1024"), Some(1024.0));
+        assert_eq!(auto("-12.5"), Some(-12.5));
+    }
+
+    #[test]
+    fn auto_single_separator() {
+        // a single comma before 3 digits is a thousands separator, otherwise a decimal one
+        assert_eq!(auto("1,024"), Some(1024.0));
+        assert_eq!(auto("1,5"), Some(1.5));
+        assert_eq!(auto("1,0245"), Some(1.0245));
+        // a single dot is always the decimal separator
+        assert_eq!(auto("1.024"), Some(1.024));
+        assert_eq!(auto("0.125"), Some(0.125));
+    }
+
+    #[test]
+    fn auto_thousands_only() {
+        assert_eq!(auto("1,234,567"), Some(1234567.0));
+        assert_eq!(auto("1.234.567"), Some(1234567.0));
+        assert_eq!(auto("1.234.567,89"), Some(1234567.89));
+        assert_eq!(auto("1,234,567.89"), Some(1234567.89));
+    }
+
+    #[test]
+    fn explicit_separator() {
+        assert_eq!(parse_number("1,024", DecimalSeparator::Comma), Some(1.024));
+        assert_eq!(parse_number("1.024", DecimalSeparator::Comma), Some(1024.0));
+        assert_eq!(
+            parse_number("1.234.567", DecimalSeparator::Comma),
+            Some(1234567.0)
+        );
+        assert_eq!(parse_number("1,024", DecimalSeparator::Dot), Some(1024.0));
+        assert_eq!(parse_number("1.024", DecimalSeparator::Dot), Some(1.024));
+        assert_eq!(parse_number("1.234.567", DecimalSeparator::Dot), None);
+    }
+
+    #[test]
+    fn rejects_malformed_numbers() {
+        assert_eq!(auto("1,2,3"), None);
+        assert_eq!(auto("12,34.5"), None);
+        assert_eq!(auto("1.2.3"), None);
+        assert_eq!(auto("abc"), None);
+        assert_eq!(auto(""), None);
+        assert_eq!(auto("NaN"), None);
+        assert_eq!(auto("inf"), None);
+    }
+}
@@ -0,0 +1,223 @@
+use std::fmt;
+
+use calamine::{DataType, Range};
+
+use crate::models::errors::MyError;
+
+/// An RVTools release, e.g. 4.1.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RvtoolsVersion(pub u32, pub u32, pub u32);
+
+impl RvtoolsVersion {
+    /// Release that renamed the MB columns to MiB
+    pub const MIB_COLUMNS: RvtoolsVersion = RvtoolsVersion(4, 1, 2);
+
+    /// Parses "4.1.2" or "4.1.2.0", missing parts are read as 0
+    pub fn parse(version: &str) -> Option<Self> {
+        let mut parts = version
+            .trim()
+            .split('.')
+            .map(|x| x.trim().parse::<u32>().ok());
+
+        let major = parts.next()??;
+        let minor = parts.next().flatten().unwrap_or(0);
+        let patch = parts.next().flatten().unwrap_or(0);
+
+        Some(RvtoolsVersion(major, minor, patch))
+    }
+}
+
+impl fmt::Display for RvtoolsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+/// Columns read from the RVTools sheets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Vm,
+    Powerstate,
+    InUse,
+    Datacenter,
+    Cluster,
+    Host,
+    Template,
+    SrmPlaceholder,
+    Consumed,
+    SnapshotName,
+    SnapshotDate,
+    SnapshotSize,
+}
+
+/// A column header and the RVTools releases that use it
+struct ColumnAlias {
+    column: Column,
+    header: &'static str,
+    since: Option<RvtoolsVersion>,
+    until: Option<RvtoolsVersion>,
+}
+
+const fn alias(column: Column, header: &'static str) -> ColumnAlias {
+    ColumnAlias {
+        column,
+        header,
+        since: None,
+        until: None,
+    }
+}
+
+const fn mib(column: Column, header: &'static str) -> ColumnAlias {
+    ColumnAlias {
+        column,
+        header,
+        since: Some(RvtoolsVersion::MIB_COLUMNS),
+        until: None,
+    }
+}
+
+const fn mb(column: Column, header: &'static str) -> ColumnAlias {
+    ColumnAlias {
+        column,
+        header,
+        since: None,
+        until: Some(RvtoolsVersion::MIB_COLUMNS),
+    }
+}
+
+/// Known headers per column, matched ignoring case and whitespace
+const ALIASES: &[ColumnAlias] = &[
+    alias(Column::Vm, "VM"),
+    alias(Column::Vm, "VM Name"),
+    alias(Column::Powerstate, "Powerstate"),
+    alias(Column::Powerstate, "Power state"),
+    mib(Column::InUse, "In Use MiB"),
+    mb(Column::InUse, "In Use MB"),
+    alias(Column::Datacenter, "Datacenter"),
+    alias(Column::Datacenter, "Data center"),
+    alias(Column::Cluster, "Cluster"),
+    alias(Column::Host, "Host"),
+    alias(Column::Template, "Template"),
+    alias(Column::SrmPlaceholder, "SRM Placeholder"),
+    mib(Column::Consumed, "Consumed MiB"),
+    mb(Column::Consumed, "Consumed MB"),
+    alias(Column::SnapshotName, "Name"),
+    alias(Column::SnapshotName, "Snapshot"),
+    alias(Column::SnapshotDate, "Date / time"),
+    alias(Column::SnapshotDate, "Date"),
+    mib(Column::SnapshotSize, "Size MiB (total)"),
+    mb(Column::SnapshotSize, "Size MB (total)"),
+];
+
+impl ColumnAlias {
+    fn in_version(&self, version: Option<RvtoolsVersion>) -> bool {
+        match version {
+            Some(v) => self.since.is_none_or(|s| v >= s) && self.until.is_none_or(|u| v < u),
+            None => true,
+        }
+    }
+}
+
+impl Column {
+    /// Headers for this column, the ones used by `version` first
+    pub fn headers(&self, version: Option<RvtoolsVersion>) -> Vec<&'static str> {
+        let (mut current, other): (Vec<_>, Vec<_>) = ALIASES
+            .iter()
+            .filter(|x| x.column == *self)
+            .partition(|x| x.in_version(version));
+        current.extend(other);
+        current.into_iter().map(|x| x.header).collect()
+    }
+}
+
+/// Lowercase with the whitespace removed
+pub fn normalize(header: &str) -> String {
+    header
+        .chars()
+        .filter(|x| !x.is_whitespace())
+        .flat_map(|x| x.to_lowercase())
+        .collect()
+}
+
+/// Header row of a sheet as strings
+pub fn header_names(range: &Range<DataType>) -> Vec<String> {
+    range
+        .rows()
+        .next()
+        .map(|row| {
+            row.iter()
+                .filter_map(|x| match x {
+                    DataType::String(s) => Some(s.to_string()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Position of the first header matching any of `names`
+pub fn find_column(range: &Range<DataType>, names: &[&str]) -> Option<usize> {
+    let row = range.rows().next()?;
+
+    names.iter().find_map(|name| {
+        let name = normalize(name);
+        row.iter().position(|x| match x {
+            DataType::String(s) => normalize(s) == name,
+            _ => false,
+        })
+    })
+}
+
+/// Column missing error listing the nearest headers in the sheet
+pub fn missing_column(range: &Range<DataType>, names: &[&str]) -> MyError {
+    let wanted = names.iter().map(|x| normalize(x)).collect::<Vec<_>>();
+
+    let mut nearest = header_names(range)
+        .into_iter()
+        .map(|header| {
+            let distance = wanted
+                .iter()
+                .map(|x| levenshtein(x, &normalize(&header)))
+                .min()
+                .unwrap_or(usize::MAX);
+            (distance, header)
+        })
+        .collect::<Vec<_>>();
+    nearest.sort();
+
+    MyError::ColumnPosition {
+        column: names.join(" / "),
+        nearest: nearest.into_iter().take(3).map(|x| x.1).collect(),
+    }
+}
+
+/// Detects the RVTools version from the header shape, the MiB columns came in 4.1.2
+pub fn version_from_headers(range: &Range<DataType>) -> Option<RvtoolsVersion> {
+    if find_column(range, &["In Use MiB"]).is_some() {
+        Some(RvtoolsVersion::MIB_COLUMNS)
+    } else if find_column(range, &["In Use MB"]).is_some() {
+        Some(RvtoolsVersion(4, 1, 1))
+    } else {
+        None
+    }
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            current.push(
+                (previous[j] + cost)
+                    .min(previous[j + 1] + 1)
+                    .min(current[j] + 1),
+            );
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
@@ -1,58 +1,225 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod coerce;
+mod columns;
+
+use std::{fs, path::Path};
+
+use anyhow::anyhow;
 use calamine::{DataType, Range};
+use serde::de::DeserializeOwned;
+
+use crate::models::{
+    errors::{CellLocation, MyError},
+    units::DecimalSeparator,
+};
 
-use crate::models::errors::MyError;
+pub use columns::{find_column, version_from_headers, Column, RvtoolsVersion};
+
+/// Excel serial date of 1970-01-01
+const UNIX_EPOCH_SERIAL: i64 = 25569;
 
 pub trait ColPosition {
-    fn get_col_pos(&self, col_name: &String) -> Result<usize, MyError>;
+    fn get_col_pos(&self, col_name: &str) -> Result<usize, MyError>;
+    fn get_column(
+        &self,
+        column: Column,
+        version: Option<RvtoolsVersion>,
+    ) -> Result<(usize, &'static str), MyError>;
 }
 
 impl ColPosition for Range<DataType> {
-    fn get_col_pos(&self, col_name: &String) -> Result<usize, MyError> {
-        let data_type = &self.rows().next().unwrap();
+    /// Position of a column by name, ignoring case and whitespace
+    fn get_col_pos(&self, col_name: &str) -> Result<usize, MyError> {
+        find_column(self, &[col_name]).ok_or_else(|| columns::missing_column(self, &[col_name]))
+    }
+
+    /// Position and header of a known column, trying the aliases of `version` first
+    fn get_column(
+        &self,
+        column: Column,
+        version: Option<RvtoolsVersion>,
+    ) -> Result<(usize, &'static str), MyError> {
+        let headers = column.headers(version);
 
-        let pos = data_type
+        headers
             .iter()
-            .position(|x| x == &DataType::String(col_name.to_string()));
+            .find_map(|x| find_column(self, &[x]).map(|pos| (pos, *x)))
+            .ok_or_else(|| columns::missing_column(self, &headers))
+    }
+}
+
+/// A sheet of a workbook, for the locations of the cells read from it
+#[derive(Debug, Clone, Copy)]
+pub struct SheetRef<'a> {
+    pub file: &'a str,
+    pub sheet: &'a str,
+}
 
-        if let Some(p) = pos {
-            Ok(p)
-        } else {
-            Err(MyError::ColumnPosition(format!("{}", col_name.to_string())))
+impl<'a> SheetRef<'a> {
+    /// `row` is 1-based as shown in Excel
+    pub fn at(&self, row: usize, column: &'a str) -> CellRef<'a> {
+        CellRef {
+            file: self.file,
+            sheet: self.sheet,
+            row,
+            column,
+        }
+    }
+}
+
+/// A cell position, turned into a `CellLocation` when the value can't be read
+#[derive(Debug, Clone, Copy)]
+pub struct CellRef<'a> {
+    pub file: &'a str,
+    pub sheet: &'a str,
+    pub row: usize,
+    pub column: &'a str,
+}
+
+impl CellRef<'_> {
+    fn error(&self, expected: &'static str, value: &DataType) -> MyError {
+        MyError::CellValue {
+            expected,
+            value: format!("{:?}", value),
+            location: CellLocation {
+                file: self.file.to_string(),
+                sheet: self.sheet.to_string(),
+                row: self.row,
+                column: self.column.to_string(),
+            },
         }
     }
 }
 
 pub trait GetString {
-    fn get_string_value(&self, item: String, row: usize) -> Result<String, MyError>;
+    fn get_string_value(&self, at: CellRef) -> Result<String, MyError>;
+    fn get_optional_string(&self, at: CellRef) -> Result<Option<String>, MyError>;
 }
 
 impl GetString for DataType {
-    fn get_string_value(&self, item: String, row: usize) -> Result<String, MyError> {
+    /// Numbers, booleans and dates are read as their text, an empty cell is an error
+    fn get_string_value(&self, at: CellRef) -> Result<String, MyError> {
+        self.get_optional_string(at)?
+            .ok_or_else(|| at.error("text", self))
+    }
+
+    /// None for an empty or blank cell
+    fn get_optional_string(&self, at: CellRef) -> Result<Option<String>, MyError> {
         match self {
-            DataType::String(t) => Ok(t.to_string()),
-            DataType::Empty => Ok("None".to_string()),
-            _ => Err(MyError::EnumToString(format!(
-                "{} - row {} - Datatype {:?}",
-                item, row, self
-            ))),
+            DataType::Empty => Ok(None),
+            DataType::String(t) if t.trim().is_empty() => Ok(None),
+            _ => coerce::to_text(self)
+                .map(Some)
+                .ok_or_else(|| at.error("text", self)),
         }
     }
 }
 
 pub trait GetFloat {
-    fn get_float_value(&self, item: String, row: usize) -> Result<f64, MyError>;
+    fn get_float_value(&self, at: CellRef, decimal: DecimalSeparator) -> Result<f64, MyError>;
 }
 
 impl GetFloat for DataType {
-    fn get_float_value(&self, item: String, row: usize) -> Result<f64, MyError> {
-        match self {
-            DataType::Float(t) => Ok(*t),
-            DataType::Int(t) => Ok(*t as f64),
-            DataType::Empty => Ok(0.0),
-            _ => Err(MyError::EnumToFloat(format!(
-                "{} - row {} - Datatype {:?}",
-                item, row, self
-            ))),
+    /// Numbers stored as text are parsed with the given decimal separator
+    fn get_float_value(&self, at: CellRef, decimal: DecimalSeparator) -> Result<f64, MyError> {
+        coerce::to_number(self, decimal).ok_or_else(|| at.error("a number", self))
+    }
+}
+
+pub trait IsTrue {
+    fn is_true(&self) -> bool;
+}
+
+impl IsTrue for DataType {
+    fn is_true(&self) -> bool {
+        coerce::to_bool(self)
+    }
+}
+
+pub trait GetDate {
+    fn get_date_value(&self, at: CellRef) -> Result<f64, MyError>;
+}
+
+impl GetDate for DataType {
+    /// Returns the Excel serial date
+    fn get_date_value(&self, at: CellRef) -> Result<f64, MyError> {
+        coerce::to_date(self).ok_or_else(|| at.error("a date", self))
+    }
+}
+
+/// Excel serial date for a civil date
+pub fn excel_serial(year: i64, month: i64, day: i64) -> f64 {
+    // days from civil, see http://howardhinnant.github.io/date_algorithms.html
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = (if y >= 0 { y } else { y - 399 }) / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let unix_days = era * 146097 + doe - 719468;
+
+    (unix_days + UNIX_EPOCH_SERIAL) as f64
+}
+
+/// Civil date of an Excel serial date, the inverse of `excel_serial`
+pub fn civil_date(serial: f64) -> (i64, i64, i64) {
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = serial.floor() as i64 - UNIX_EPOCH_SERIAL + 719468;
+    let era = (if z >= 0 { z } else { z - 146096 }) / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Excel serial date for the current time
+pub fn today_serial() -> f64 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs_f64())
+        .unwrap_or_default();
+
+    seconds / 86400.0 + UNIX_EPOCH_SERIAL as f64
+}
+
+/// Reads a JSON file, errors give the path of the value that doesn't fit, e.g.
+/// "[0].dc_names: invalid type: string "DC1", expected a sequence at line 3 column 22"
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    let text = fs::read_to_string(path).map_err(|e| anyhow!("Reading {:?}: {}", path, e))?;
+    let deserializer = &mut serde_json::Deserializer::from_str(&text);
+
+    serde_path_to_error::deserialize(deserializer).map_err(|e| anyhow!("{:?}: {}", path, e))
+}
+
+/// Reads a YAML file, serde_yaml errors already give the path of the value that doesn't fit
+pub fn load_yaml<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    let text = fs::read_to_string(path).map_err(|e| anyhow!("Reading {:?}: {}", path, e))?;
+
+    serde_yaml::from_str(&text).map_err(|e| anyhow!("{:?}: {}", path, e))
+}
+
+/// Loads a list of records from a CSV file with a header row or a JSON array, by extension
+pub fn load_records<T: DeserializeOwned>(path: &Path) -> anyhow::Result<Vec<T>> {
+    let extension = path
+        .extension()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    match extension.as_str() {
+        "csv" => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_path(path)?;
+            Ok(reader.deserialize().collect::<Result<Vec<_>, _>>()?)
         }
+        "json" => load_json(path),
+        _ => Err(anyhow!("File must be .csv or .json, got {:?}", path)),
     }
 }
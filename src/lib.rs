@@ -1,11 +1,27 @@
-mod excel;
+pub mod bands;
+mod diagnostics;
+pub mod excel;
+pub mod growth;
 mod helpers;
-mod models;
+pub mod models;
+mod nas;
+mod physical;
+pub mod pipeline;
 mod plot;
-mod vse;
+pub mod report;
+pub mod schemas;
+mod serve;
+pub mod sitemap;
+pub mod sizing;
+pub mod snapshot;
+pub mod split;
+pub mod stats;
+mod tui;
+pub mod vse;
+pub mod window;
 use std::{fs, io::Write, println};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Parser;
 use comfy_table::{
     modifiers::{UTF8_ROUND_CORNERS, UTF8_SOLID_INNER_BORDERS},
@@ -18,173 +34,99 @@ use crate::{
     excel::get_excel,
     models::{
         cli::Cli,
-        new_model::Mapper,
-        rvtools::{Datacenter, Vinfo, Vpartition},
+        rvtools::Vinfo,
+        units::{Capacity, Unit},
     },
-    vse::vse_construct,
+    pipeline::{site_for_vm, Settings},
+    report::Report,
+    stats::VmStats,
 };
 use std::collections::HashMap;
 
 pub fn run() -> Result<()> {
     let cli = Cli::parse();
 
-    if cli.dc_site_map_template {
-        let mapper = vec![Mapper {
-            group_name: "Group1".to_string(),
-            dc_names: vec!["DC1".to_string(), "DC2".to_string()],
-        }];
+    if let Some(dir) = &cli.write_schemas {
+        return schemas::write(dir);
+    }
 
-        let mapper_json = serde_json::to_string_pretty(&mapper)?;
+    if let Some(addr) = &cli.serve {
+        return serve::run(addr);
+    }
 
-        let mut json_file = fs::File::create("dc_mapper.json")?;
-        json_file.write_all(mapper_json.as_bytes())?;
+    if let Some(vse_file) = &cli.vse_file {
+        let vse_string = fs::read_to_string(vse_file)?;
+        let vse = vse::from_str(&vse_string).map_err(|e| anyhow!("{:?}: {}", vse_file, e))?;
 
-        println!("dc_mapper.json created");
+        sizing::print_estimate(&sizing::estimate(&vse));
+        if cli.window_check {
+            window::print_check(&window::check(&vse, cli.max_throughput), cli.max_throughput);
+        }
         return Ok(());
     }
 
-    let (info_vec, part_vec) = get_excel(&cli)?;
-
-    let devisor = 1024_f64.powf(2.0);
-
-    let grouped: Vec<Vpartition> = part_vec
-        .into_iter()
-        .sorted_by_key(|x| x.vm_name.clone())
-        .group_by(|x| x.vm_name.clone())
-        .into_iter()
-        .map(|(name, group)| {
-            let total = group.map(|x| x.capacity).sum();
-            Vpartition {
-                vm_name: name,
-                capacity: total,
-            }
-        })
-        .collect();
-
-    let mut combined: Vec<Vinfo> = Vec::new();
-    let mut group_map = HashMap::new();
-
-    for (i, j) in grouped.iter().enumerate() {
-        group_map.insert(j.vm_name.clone(), i);
-    }
+    let mut settings = Settings::from_cli(&cli)?;
 
-    if !cli.do_not_use_vpartition {
-        for i in &info_vec {
-            if let Some(&j_idx) = group_map.get(&i.vm_name) {
-                let j = &grouped[j_idx];
-                let low_cap = f64::min(i.capacity, j.capacity);
-
-                let new_st = Vinfo {
-                    vm_name: i.vm_name.clone(),
-                    datacenter: i.datacenter.clone(),
-                    cluster: i.cluster.clone(),
-                    capacity: low_cap,
-                    powerstate: i.powerstate.clone(),
-                };
-                combined.push(new_st);
-            } else {
-                combined.push(i.clone());
-            }
-        }
-    } else {
-        combined = info_vec.clone()
-    }
+    let data = get_excel(&cli)?;
 
-    let mut datacenters: Vec<Datacenter> = Vec::new();
+    if let Some(path) = &cli.dc_site_map_template {
+        let mappers = sitemap::template(&data.info, cli.template_clusters);
+        sitemap::write_template(path, &mappers, cli.force)?;
 
-    // Flattens the DC results into single clusters
-    if cli.flatten_site && !cli.flatten && cli.dc_site_map.is_none() {
-        combined
-            .iter()
-            .sorted_by_key(|s| &s.datacenter)
-            .group_by(|s| &s.datacenter)
-            .into_iter()
-            .for_each(|(key, group)| {
-                let mut cap = 0.0;
-                let mut vm_count = 0;
-                group.for_each(|x| {
-                    cap += x.capacity;
-                    vm_count += 1;
-                });
-
-                datacenters.push(Datacenter {
-                    name: key.to_string(),
-                    cluster: format!("{}_cluster", key.to_string()),
-                    vm_count,
-                    capacity: cap / devisor,
-                })
-            });
-    } else {
-        combined
-            .iter()
-            .sorted_by_key(|s| (&s.datacenter, &s.cluster))
-            .group_by(|s| (&s.datacenter, &s.cluster))
-            .into_iter()
-            .for_each(|(key, group)| {
-                let mut cap = 0.0;
-                let mut vm_count = 0;
-                group.for_each(|x| {
-                    cap += x.capacity;
-                    vm_count += 1;
-                });
-
-                datacenters.push(Datacenter {
-                    name: key.0.to_string(),
-                    cluster: key.1.to_string(),
-                    vm_count,
-                    capacity: cap / devisor,
-                })
-            });
+        println!("{} created with {} groups", path.display(), mappers.len());
+        return Ok(());
     }
 
-    if cli.flatten && !cli.flatten_site && cli.dc_site_map.is_none() {
-        let vm_count: usize = datacenters.iter().map(|x| x.vm_count).sum();
-        let capacity: f64 = datacenters.iter().map(|x| x.capacity).sum();
-
-        datacenters = vec![];
+    if let Some(baseline_files) = &cli.baseline_rvtools {
+        let baseline_cli = Cli {
+            rvtools_files: baseline_files.clone(),
+            ..cli.clone()
+        };
+        let baseline = get_excel(&baseline_cli)?;
+
+        let site_capacity = |info: &[Vinfo]| {
+            let mut map: HashMap<String, Capacity> = HashMap::new();
+            info.iter().for_each(|x| {
+                if let Some(site) = site_for_vm(x, &cli, &settings.dc_map, &settings.shape) {
+                    *map.entry(site).or_default() += x.capacity;
+                }
+            });
+            map
+        };
+
+        let measured = growth::measure(
+            &site_capacity(&baseline.info),
+            &site_capacity(&data.info),
+            cli.baseline_age_days,
+        );
+        settings.growth.add_measured(measured);
+    }
 
-        datacenters.push(Datacenter {
-            name: "DC1".to_string(),
-            cluster: "Cluster1".to_string(),
-            vm_count,
-            capacity,
-        })
+    let inventory = pipeline::prepare(&cli, data, &settings)?;
+
+    if cli.tui {
+        return tui::run(
+            &cli,
+            inventory.vms.clone(),
+            settings.shape.clone(),
+            settings.dc_map.clone(),
+            |vms, shape| {
+                let datacenters = settings.workloads(&cli, &inventory, vms, shape);
+                settings.vse(&cli, &inventory.nas_shares, &datacenters)
+            },
+        );
     }
 
-    if let Some(dc_map) = cli.dc_site_map {
-        let mapper_file = fs::read_to_string(dc_map)?;
-        let dc_map: Vec<Mapper> = serde_json::from_str(&mapper_file)?;
-
-        let mut temp_dc: Vec<Datacenter> = Vec::new();
-        dc_map.iter().for_each(|map_item| {
-            let mut cap = 0.0;
-            let mut vm_count = 0;
-
-            map_item.dc_names.iter().for_each(|site| {
-                let dc_cap: f64 = datacenters
-                    .iter()
-                    .filter(|x| x.name.contains(&*site))
-                    .map(|x| x.capacity)
-                    .sum();
-                let dc_vm_count: usize = datacenters
-                    .iter()
-                    .filter(|x| x.name.contains(&*site))
-                    .map(|x| x.vm_count)
-                    .sum();
-                cap += dc_cap;
-                vm_count += dc_vm_count;
-            });
+    let datacenters = settings.workloads(&cli, &inventory, &inventory.vms, &settings.shape);
 
-            temp_dc.push(Datacenter {
-                name: map_item.group_name.clone(),
-                cluster: format!("{}_cluster", map_item.group_name),
-                vm_count,
-                capacity: cap,
-            })
-        });
+    let combined = inventory
+        .vms
+        .iter()
+        .filter(|x| !settings.shape.is_excluded(x))
+        .cloned()
+        .collect::<Vec<_>>();
 
-        datacenters = temp_dc;
-    }
+    let vm_stats = VmStats::from_vms(&combined, &cli)?;
 
     if cli.show_info {
         let mut table = Table::new();
@@ -193,7 +135,12 @@ pub fn run() -> Result<()> {
             .load_preset(UTF8_FULL)
             .apply_modifier(UTF8_ROUND_CORNERS)
             .apply_modifier(UTF8_SOLID_INNER_BORDERS)
-            .set_header(vec!["Datacenter", "Cluster", "Capacity (TB)", "VM Count"]);
+            .set_header(vec![
+                "Datacenter".to_string(),
+                "Cluster".to_string(),
+                format!("Capacity ({})", cli.unit),
+                "VM Count".to_string(),
+            ]);
 
         datacenters
             .iter()
@@ -203,13 +150,15 @@ pub fn run() -> Result<()> {
                 table.add_row(vec![
                     x.name.to_string(),
                     x.cluster.to_string(),
-                    format!("{:.2}", x.capacity),
+                    format!("{:.2}", x.capacity.to(cli.unit)),
                     x.vm_count.to_string(),
                 ]);
             });
         println!("{table}");
     }
 
+    let vm_unit = cli.unit.vm_unit();
+
     if cli.vm_table_print {
         let mut table = Table::new();
 
@@ -218,16 +167,14 @@ pub fn run() -> Result<()> {
             .apply_modifier(UTF8_ROUND_CORNERS)
             .apply_modifier(UTF8_SOLID_INNER_BORDERS)
             .set_header(vec![
-                "Datacenter",
-                "Cluster",
-                "VM Name",
-                "Capacity (GiB)",
-                "vPartition (GiB)",
-                "Power State",
+                "Datacenter".to_string(),
+                "Cluster".to_string(),
+                "VM Name".to_string(),
+                format!("Capacity ({})", vm_unit),
+                format!("vPartition ({})", vm_unit),
+                "Power State".to_string(),
             ]);
 
-        let gb_devisor = 1024_f64.powf(1.0);
-
         combined
             .iter()
             .sorted_by(|a, b| a.capacity.partial_cmp(&b.capacity).unwrap())
@@ -237,31 +184,59 @@ pub fn run() -> Result<()> {
                     x.datacenter.to_string(),
                     x.cluster.to_string(),
                     x.vm_name.to_string(),
-                    format!("{:.2}", x.capacity / gb_devisor),
-                    format!("{:.2}", x.capacity / gb_devisor),
+                    format!("{:.2}", x.capacity.to(vm_unit)),
+                    format!("{:.2}", x.capacity.to(vm_unit)),
                     x.powerstate.to_string(),
                 ]);
             });
         println!("{table}");
     }
 
-    let datacenter_strings = datacenters
-        .iter()
-        .map(|x| format!("{}", x.name))
-        .sorted()
-        .dedup()
-        .collect::<Vec<_>>();
+    if cli.vm_stats {
+        stats::print_tables(&vm_stats);
+    }
+
+    let forecast = growth::forecast(&datacenters, &settings.growth, cli.unit);
+
+    if cli.forecast {
+        growth::print_forecast(&forecast, cli.unit);
+    }
+
+    if cli.snapshot_report {
+        snapshot::print_report(&inventory.snapshots);
+    }
+
+    if let Some(max_age_days) = cli.snapshot_age_days {
+        snapshot::print_findings(&inventory.snapshots, max_age_days);
+    }
 
     if cli.dc_print {
-        datacenter_strings.iter().for_each(|x| println!("{:?},", x))
+        pipeline::site_names(&datacenters)
+            .iter()
+            .for_each(|x| println!("{:?},", x))
     }
 
-    let vse = vse_construct(datacenter_strings, &datacenters, cli.retention)?;
+    let (vse, ids) = settings.vse_with_ids(&cli, &inventory.nas_shares, &datacenters)?;
+
+    if let Some(id_map) = &cli.id_map {
+        vse::save_map(id_map, &ids)?;
+    }
+
+    // split files keep the ids of the combined file
+    settings.ids = ids;
 
     if cli.print {
         println!("{:#?}", vse);
     }
 
+    if cli.estimate {
+        sizing::print_estimate(&sizing::estimate(&vse));
+    }
+
+    if cli.window_check {
+        window::print_check(&window::check(&vse, cli.max_throughput), cli.max_throughput);
+    }
+
     if cli.print_json {
         let combined_json = serde_json::to_string_pretty(&combined)?;
         println!("{}", combined_json);
@@ -270,31 +245,73 @@ pub fn run() -> Result<()> {
     if cli.plot {
         let data = datacenters
             .iter()
-            .filter(|x| x.capacity > 1.00)
-            .map(|x| x.capacity)
+            .filter(|x| x.capacity.to(Unit::Tb) > 1.00)
+            .map(|x| x.capacity.to(cli.unit))
             .collect::<Vec<_>>();
         let site_names = datacenters
             .iter()
-            .filter(|x| x.capacity > 1.00)
+            .filter(|x| x.capacity.to(Unit::Tb) > 1.00)
             .map(|x| x.name.clone())
             .collect::<Vec<_>>();
-        plot::plot_data(data, site_names);
+        plot::plot_data(data, site_names, cli.unit);
     }
 
     if !cli.print_json {
         let total_vms = combined.len();
         println!("Total VMs: {}", total_vms);
 
-        let total_cap = datacenters.iter().fold(0.0, |acc, x| acc + x.capacity);
+        if inventory.physical_count > 0 {
+            println!("Total Physical Servers: {}", inventory.physical_count);
+        }
 
-        println!("Total Capacity: {:.2} TB", total_cap);
+        if !inventory.nas_shares.is_empty() {
+            println!(
+                "Total NAS Shares: {} ({:.2} {})",
+                inventory.nas_shares.len(),
+                nas::total_capacity(&inventory.nas_shares).to(cli.unit),
+                cli.unit
+            );
+        }
+
+        let total_cap: Capacity = datacenters.iter().map(|x| x.capacity).sum();
+
+        println!("Total Capacity: {:.2} {}", total_cap.to(cli.unit), cli.unit);
+
+        let vm_cap: Capacity = datacenters
+            .iter()
+            .filter(|x| x.workload_type == "VM")
+            .map(|x| x.capacity)
+            .sum();
+
+        let average_vm = vm_cap.to(vm_unit) / total_vms as f64;
 
-        let average_vm = (total_cap * 1024.0) / total_vms as f64;
+        println!("Average VM Size: {:.2} {}", average_vm, vm_unit);
 
-        println!("Average VM Size: {:.2} GB", average_vm);
+        stats::print_summary(&vm_stats);
+
+        inventory
+            .excluded
+            .iter()
+            .for_each(|(kind, count)| println!("Excluded {}: {}", kind, count));
+
+        diagnostics::print_summary(&inventory.warnings);
     }
 
-    if let Some(mut file_name) = cli.output_file {
+    if let Some(report_file) = &cli.report_file {
+        let report = Report {
+            vm_stats: Some(vm_stats),
+            ..Report::from_datacenters(&datacenters, forecast, cli.unit)
+        };
+
+        report::write(report_file, &report)?;
+    }
+
+    if let (Some(split_by), Some(file_name)) = (cli.split_by, &cli.output_file) {
+        let prefix = file_name.trim_end_matches(".json");
+        let partitions = split::partitions(split_by, &cli, &settings, &inventory);
+
+        split::write(prefix, split_by, &cli, &settings, &partitions)?;
+    } else if let Some(mut file_name) = cli.output_file {
         if !file_name.contains(".json") {
             file_name.push_str(".json");
         }
@@ -1,18 +1,56 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
-#[derive(Parser, Debug)]
+use super::units::{DecimalSeparator, Unit};
+
+#[derive(Parser, Debug, Clone)]
 #[clap(author, version, about)]
 pub struct Cli {
-    /// RvTools File(s)
+    /// RvTools File(s), - reads a file from stdin
     #[clap(short, long, value_delimiter = ',', num_args = 1..)]
     pub rvtools_files: Vec<PathBuf>,
 
+    /// Number of RVTools files to load in parallel, defaults to the CPU count
+    #[clap(long, value_parser)]
+    pub jobs: Option<usize>,
+
+    /// Fail on the first unreadable file, sheet or cell instead of skipping it with a warning
+    #[clap(long, action, default_value_t = false)]
+    pub strict: bool,
+
+    /// Decimal separator of numbers stored as text, use comma for European exports
+    #[clap(long, value_enum, default_value_t = DecimalSeparator::Auto)]
+    pub decimal_separator: DecimalSeparator,
+
     /// Include Powered Off VMs
     #[clap(short, long, action, default_value_t = false)]
     pub include_powered_off: bool,
 
+    /// Include VM templates
+    #[clap(long, action, default_value_t = false)]
+    pub include_templates: bool,
+
+    /// Include SRM placeholder VMs
+    #[clap(long, action, default_value_t = false)]
+    pub include_srm_placeholders: bool,
+
+    /// Include Veeam replica VMs
+    #[clap(long, action, default_value_t = false)]
+    pub include_replicas: bool,
+
+    /// VM name suffix used to detect Veeam replicas
+    #[clap(long, value_parser, default_value = "_replica")]
+    pub replica_suffix: String,
+
+    /// Cluster name for VMs on hosts outside a cluster - {dc} and {host} are replaced
+    #[clap(long, value_parser, default_value = "{dc}-standalone")]
+    pub standalone_cluster: String,
+
+    /// Datacenter name for VMs with an empty Datacenter cell - {host} is replaced
+    #[clap(long, value_parser, default_value = "Unknown")]
+    pub missing_datacenter: String,
+
     /// Retention - example 30D1W1M1Y - global
     #[clap(long, value_delimiter = ',', num_args = 1..)]
     pub retention: Option<String>,
@@ -21,6 +59,10 @@ pub struct Cli {
     #[clap(short, long, value_parser)]
     pub output_file: Option<String>,
 
+    /// Write one VSE file per site, datacenter or RVTools file, plus an index file - uses the output file name as the prefix
+    #[clap(long, value_enum, requires = "output_file")]
+    pub split_by: Option<SplitBy>,
+
     /// Print converted data (VSE format)
     #[clap(short, long, action, default_value_t = false)]
     pub print: bool,
@@ -57,18 +99,98 @@ pub struct Cli {
     #[clap(long, action, default_value_t = false)]
     pub legacy: bool,
 
-    /// Map DCs to a site - requires a JSON file
+    /// Map DCs to a site - JSON, YAML or CSV file
     #[clap(long, value_parser)]
     pub dc_site_map: Option<PathBuf>,
 
-    /// Creates Map DC JSON template
+    /// Creates a DC site map JSON template from the datacenters in the RVTools files [default: dc_mapper.json]
+    #[clap(long, value_parser, num_args = 0..=1, default_missing_value = "dc_mapper.json", requires = "rvtools_files")]
+    pub dc_site_map_template: Option<PathBuf>,
+
+    /// List the clusters of each group in the DC site map template
     #[clap(long, action, default_value_t = false)]
-    pub dc_site_map_template: bool,
+    pub template_clusters: bool,
+
+    /// Overwrite an existing DC site map template file
+    #[clap(long, action, default_value_t = false)]
+    pub force: bool,
+
+    /// Writes the JSON Schemas of the VSE, DC site map and config files to a directory
+    #[clap(long, value_parser)]
+    pub write_schemas: Option<PathBuf>,
 
     /// Don't use vPartition capacity
     #[clap(short, long, action, default_value_t = false)]
     pub do_not_use_vpartition: bool,
 
+    /// Physical servers to add as agent workloads - CSV or JSON file
+    #[clap(long, value_parser)]
+    pub physical: Option<PathBuf>,
+
+    /// NAS shares to add as file share workloads - CSV or JSON file
+    #[clap(long, value_parser)]
+    pub nas: Option<PathBuf>,
+
+    /// Treat VMs matching this regex as file servers and size them as NAS shares
+    #[clap(long, value_parser)]
+    pub nas_vm_pattern: Option<String>,
+
+    /// Estimated files per GB for NAS shares derived from file server VMs
+    #[clap(long, value_parser, default_value_t = 1000.0)]
+    pub nas_files_per_gb: f64,
+
+    /// NAS retention - example 30D1W1M1Y
+    #[clap(long, value_parser)]
+    pub nas_retention: Option<String>,
+
+    /// Project length in years
+    #[clap(long, value_parser, default_value_t = 3)]
+    pub project_length: i64,
+
+    /// Global annual growth rate (%)
+    #[clap(long, value_parser, default_value_t = 10.0)]
+    pub growth_rate: f64,
+
+    /// Site annual growth rates (%) - SITE=PERCENT
+    #[clap(long, value_delimiter = ',', num_args = 1..)]
+    pub site_growth: Option<Vec<String>>,
+
+    /// Older RVTools file(s) to measure the site growth rates from
+    #[clap(long, value_delimiter = ',', num_args = 1..)]
+    pub baseline_rvtools: Option<Vec<PathBuf>>,
+
+    /// Age of the baseline RVTools file(s) in days
+    #[clap(long, value_parser, default_value_t = 365.0)]
+    pub baseline_age_days: f64,
+
+    /// Print the capacity growth forecast per site and cluster
+    #[clap(long, action, default_value_t = false)]
+    pub forecast: bool,
+
+    /// Write a summary report - JSON, or HTML for a .html file
+    #[clap(long, value_parser)]
+    pub report_file: Option<PathBuf>,
+
+    /// Serve the conversion over HTTP on this address, e.g. 127.0.0.1:8080
+    #[clap(long, value_parser)]
+    pub serve: Option<String>,
+
+    /// Explore the inventory in a terminal UI and write the VSE and shape config from it
+    #[clap(long, action, default_value_t = false)]
+    pub tui: bool,
+
+    /// Exclusions and cluster site/retention assignments - JSON file written by --tui
+    #[clap(long, value_parser)]
+    pub shape_config: Option<PathBuf>,
+
+    /// Keeps the VSE ids stable across runs - JSON file of object keys to ids, updated on each run
+    #[clap(long, value_parser)]
+    pub id_map: Option<PathBuf>,
+
+    /// Capacity unit for the summary tables
+    #[clap(long, value_enum, default_value_t = Unit::Tib)]
+    pub unit: Unit,
+
     /// Print DCs
     #[clap(long, action, default_value_t = false)]
     pub dc_print: bool,
@@ -77,6 +199,34 @@ pub struct Cli {
     #[clap(short, long, action, default_value_t = false)]
     pub vm_table_print: bool,
 
+    /// Print VM size statistics per cluster and the largest VMs
+    #[clap(long, action, default_value_t = false)]
+    pub vm_stats: bool,
+
+    /// Sizes in GiB to count the VMs above in the VM size statistics
+    #[clap(long, value_delimiter = ',', num_args = 1.., default_value = "1024,10240")]
+    pub size_thresholds: Vec<f64>,
+
+    /// Number of largest VMs in the VM size statistics
+    #[clap(long, value_parser, default_value_t = 10)]
+    pub top_vms: usize,
+
+    /// Group workloads by vInfo columns, e.g. Cluster,Tier or Folder:2 for a folder path depth
+    #[clap(long, value_delimiter = ',', num_args = 1..)]
+    pub group_by: Option<Vec<String>>,
+
+    /// Split each cluster's VMs into size band workloads
+    #[clap(long, action, default_value_t = false)]
+    pub size_bands: bool,
+
+    /// Size band limits in GiB
+    #[clap(long, value_delimiter = ',', num_args = 1.., default_value = "500,2048")]
+    pub band_limits: Vec<f64>,
+
+    /// Size bands starting at or above this size (GiB) are set to large block
+    #[clap(long, value_parser, default_value_t = 2048.0)]
+    pub large_block_gib: f64,
+
     /// Flatten to single site, repo and workload
     #[clap(long, action, default_value_t = false)]
     pub flatten: bool,
@@ -92,4 +242,55 @@ pub struct Cli {
     /// Anonymize the data
     #[clap(long, action, default_value_t = false)]
     pub anonymize: bool,
+
+    /// Print an estimate of the performance tier repository capacity per site
+    #[clap(long, action, default_value_t = false)]
+    pub estimate: bool,
+
+    /// Estimate repository capacity from an existing VSE JSON file
+    #[clap(long, value_parser)]
+    pub vse_file: Option<PathBuf>,
+
+    /// Global backup window in hours - FULL:INCREMENTAL
+    #[clap(long, value_parser, default_value = "24:12")]
+    pub backup_window: String,
+
+    /// Site backup windows - SITE=FULL:INCREMENTAL
+    #[clap(long, value_delimiter = ',', num_args = 1..)]
+    pub site_window: Option<Vec<String>>,
+
+    /// Workload class backup windows - CLASS=FULL:INCREMENTAL, e.g. VM=24:8
+    #[clap(long, value_delimiter = ',', num_args = 1..)]
+    pub class_window: Option<Vec<String>>,
+
+    /// Check the backup window throughput per site
+    #[clap(long, action, default_value_t = false)]
+    pub window_check: bool,
+
+    /// Proxy or network throughput available per site (MB/s)
+    #[clap(long, value_parser, default_value_t = 1000.0)]
+    pub max_throughput: f64,
+
+    /// Print the per-VM snapshot report from the vSnapshot sheet
+    #[clap(long, action, default_value_t = false)]
+    pub snapshot_report: bool,
+
+    /// Subtract snapshot size from the vInfo in use capacity
+    #[clap(long, action, default_value_t = false)]
+    pub subtract_snapshots: bool,
+
+    /// List VMs with snapshots older than N days
+    #[clap(long, value_parser)]
+    pub snapshot_age_days: Option<i64>,
+}
+
+/// How --split-by partitions the workloads into VSE files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SplitBy {
+    /// The site after the DC site map, with the physical servers and NAS shares of the site
+    Site,
+    /// The vInfo datacenter
+    Datacenter,
+    /// The RVTools file the VMs were read from
+    File,
 }
@@ -1,20 +1,103 @@
+use std::fmt;
+
+use serde::Serialize;
 use thiserror::Error;
 
-#[allow(dead_code)]
 #[derive(Error, Debug)]
 pub enum MyError {
-    #[error("Could not convert enum to string: {0}")]
-    EnumToString(String),
-    #[error("Could not convert enum to float: {0}")]
-    EnumToFloat(String),
-    #[error("Could not get position of column: {0}")]
-    ColumnPosition(String),
-    #[error("Error with excel file")]
+    #[error("Could not read {expected} from {value} - {location}")]
+    CellValue {
+        expected: &'static str,
+        value: String,
+        location: CellLocation,
+    },
+    #[error("Could not get position of column: {column} - nearest columns: {}", .nearest.join(", "))]
+    ColumnPosition {
+        column: String,
+        nearest: Vec<String>,
+    },
+    #[error("{sheet} sheet not found")]
+    MissingSheet { sheet: String },
+    #[error("{file}: {source}")]
+    InFile {
+        file: String,
+        #[source]
+        source: Box<MyError>,
+    },
+    #[error("Error with excel file: {0}")]
     ExcelError(#[from] calamine::XlsxError),
-    #[error("Error with vInfo sheet: {0}")]
-    VinfoError(String),
-    #[error("Error with vPartition sheet: {0}")]
-    VpartitionError(String),
     #[error("RvTools selection error: {0}")]
     RvtoolsError(String),
 }
+
+impl MyError {
+    /// Adds the file name, unless the error already has a location
+    pub fn in_file(self, file: &str) -> Self {
+        match self {
+            MyError::CellValue { .. } | MyError::InFile { .. } => self,
+            error => MyError::InFile {
+                file: file.to_string(),
+                source: Box::new(error),
+            },
+        }
+    }
+}
+
+/// Where a cell was read from, the row is 1-based as shown in Excel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CellLocation {
+    pub file: String,
+    pub sheet: String,
+    pub row: usize,
+    pub column: String,
+}
+
+impl fmt::Display for CellLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} - {} - row {} - column '{}'",
+            self.file, self.sheet, self.row, self.column
+        )
+    }
+}
+
+/// A problem skipped over in lenient mode, with as much of the location as is known
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub file: String,
+    pub sheet: Option<String>,
+    pub row: Option<usize>,
+    pub column: Option<String>,
+    pub message: String,
+    /// The cell value that couldn't be read
+    pub value: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(file: &str, sheet: Option<&str>, error: MyError) -> Self {
+        match error {
+            MyError::CellValue {
+                expected,
+                value,
+                location,
+            } => Diagnostic {
+                file: location.file,
+                sheet: Some(location.sheet),
+                row: Some(location.row),
+                column: Some(location.column),
+                message: format!("Could not read {}", expected),
+                value: Some(value),
+            },
+            MyError::InFile { file, source } => Diagnostic::new(&file, sheet, *source),
+            error => Diagnostic {
+                file: file.to_string(),
+                sheet: sheet.map(str::to_string),
+                row: None,
+                column: None,
+                message: error.to_string(),
+                value: None,
+            },
+        }
+    }
+}
@@ -1,4 +1,8 @@
 pub mod cli;
 pub mod errors;
+pub mod nas;
 pub mod new_model;
+pub mod physical;
 pub mod rvtools;
+pub mod shape;
+pub mod units;
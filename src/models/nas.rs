@@ -0,0 +1,15 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A NAS/file share workload, read from the --nas file or derived from file server VMs
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NasShare {
+    pub name: String,
+    pub site: String,
+    /// Share size in GB
+    pub size_gb: f64,
+    pub files: i64,
+    /// Site to copy the share backups to
+    #[serde(default)]
+    pub copy_site: Option<String>,
+}
@@ -1,8 +1,10 @@
+#![allow(clippy::too_many_arguments)]
 use derive_new::new;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, new)]
 #[serde(rename_all = "camelCase")]
 pub struct NewVse {
     pub project_length: i64,
@@ -17,14 +19,14 @@ pub struct NewVse {
     pub rounding: String,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, new)]
 #[serde(rename_all = "camelCase")]
 pub struct Site {
     pub id: String,
     pub name: String,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, new)]
 #[serde(rename_all = "camelCase")]
 pub struct PerfTierRepo {
     pub repo_id: String,
@@ -43,7 +45,7 @@ pub struct PerfTierRepo {
     pub archive_tier_standalone: bool,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, new)]
 #[serde(rename_all = "camelCase")]
 pub struct CapArchTier {
     pub id: String,
@@ -52,7 +54,7 @@ pub struct CapArchTier {
     pub default: bool,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, new)]
 #[serde(rename_all = "camelCase")]
 pub struct DataProperty {
     pub data_property_id: String,
@@ -63,7 +65,7 @@ pub struct DataProperty {
     pub default: bool,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, new)]
 #[serde(rename_all = "camelCase")]
 pub struct Window {
     pub backup_window_id: String,
@@ -73,7 +75,7 @@ pub struct Window {
     pub default: bool,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, new)]
 #[serde(rename_all = "camelCase")]
 pub struct Retentions {
     pub retention_id: String,
@@ -86,7 +88,7 @@ pub struct Retentions {
     pub default: bool,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, new)]
 #[serde(rename_all = "camelCase")]
 pub struct Workload {
     pub workload_id: String,
@@ -104,7 +106,7 @@ pub struct Workload {
     pub copies: Backup,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, new)]
 #[serde(rename_all = "camelCase")]
 pub struct Backup {
     pub retention_id: String,
@@ -112,7 +114,7 @@ pub struct Backup {
     pub backup_window_id: String,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, new)]
 #[serde(rename_all = "camelCase")]
 pub struct Copy {
     pub retention_id: String,
@@ -120,12 +122,63 @@ pub struct Copy {
     pub backup_window_id: String,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+/// A site made of the datacenters whose names contain any of `dc_names`
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, new)]
+#[serde(deny_unknown_fields)]
 pub struct Mapper {
+    /// Site name
     pub group_name: String,
+    /// Datacenter names, or parts of them
     pub dc_names: Vec<String>,
+    /// Only these clusters of the datacenters, all of them when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clusters: Option<Vec<String>>,
+    /// Site the backups are copied to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copy_site: Option<String>,
+    /// Retention in the 30D1W1M1Y pattern, overriding the global --retention
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retention: Option<String>,
 }
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, new)]
+
+/// A row of a CSV DC site map, for the datacenter and optionally one of its clusters
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SiteMapRow {
+    /// Datacenter name, or part of it
+    pub datacenter: String,
+    /// All the clusters of the datacenter when empty
+    pub cluster: Option<String>,
+    pub site: String,
+    pub copy_site: Option<String>,
+    pub retention: Option<String>,
+}
+
+impl From<SiteMapRow> for Mapper {
+    fn from(row: SiteMapRow) -> Self {
+        Mapper {
+            group_name: row.site,
+            dc_names: vec![row.datacenter],
+            clusters: row.cluster.map(|x| vec![x]),
+            copy_site: row.copy_site,
+            retention: row.retention,
+        }
+    }
+}
+
+impl Mapper {
+    /// Whether the group takes the VMs of this vInfo datacenter and cluster
+    pub fn matches(&self, datacenter: &str, cluster: &str) -> bool {
+        self.dc_names
+            .iter()
+            .any(|x| datacenter.contains(x.as_str()))
+            && self
+                .clusters
+                .as_ref()
+                .is_none_or(|x| x.iter().any(|c| c == cluster))
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, new)]
 #[serde(rename_all = "camelCase")]
 pub struct WorkloadNas {
     pub backup: Backup,
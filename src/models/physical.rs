@@ -0,0 +1,19 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A physical server protected by an agent, read from the --physical file
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PhysicalServer {
+    pub name: String,
+    pub site: String,
+    /// Used capacity in GB
+    pub used_gb: f64,
+    pub os: String,
+    /// VSE workload type, defaults to "Agent"
+    #[serde(rename = "type", default = "default_type")]
+    pub workload_type: String,
+}
+
+fn default_type() -> String {
+    "Agent".to_string()
+}
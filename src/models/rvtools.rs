@@ -1,18 +1,77 @@
+use std::{collections::BTreeMap, fmt};
+
 use serde::Serialize;
 
+use super::{errors::Diagnostic, units::Capacity};
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Vinfo {
     pub vm_name: String,
     pub datacenter: String,
     pub cluster: String,
-    pub capacity: f64,
+    pub capacity: Capacity,
     pub powerstate: String,
+    pub kind: VmKind,
+    /// Workload group key when grouping by vInfo columns, the cluster is used otherwise
+    pub group: Option<String>,
+    /// RVTools file the VM was read from
+    pub source: String,
+}
+
+impl Vinfo {
+    pub fn group_key(&self) -> &str {
+        self.group.as_deref().unwrap_or(&self.cluster)
+    }
+}
+
+/// Classification of a vInfo row, only `Vm` is a backup workload by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum VmKind {
+    Vm,
+    Template,
+    SrmPlaceholder,
+    Replica,
+}
+
+impl fmt::Display for VmKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            VmKind::Vm => "VMs",
+            VmKind::Template => "Templates",
+            VmKind::SrmPlaceholder => "SRM placeholders",
+            VmKind::Replica => "Veeam replicas",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Vpartition {
     pub vm_name: String,
-    pub capacity: f64,
+    pub capacity: Capacity,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Vsnapshot {
+    pub vm_name: String,
+    /// RVTools file the snapshot was read from, VM names only identify a VM within a file
+    pub source: String,
+    pub name: String,
+    /// Excel serial date (days since 1899-12-30)
+    pub date: f64,
+    pub capacity: Capacity,
+}
+
+/// The rows read from all the RVTools files
+#[derive(Debug, Clone, Default)]
+pub struct RvtoolsData {
+    pub info: Vec<Vinfo>,
+    pub partitions: Vec<Vpartition>,
+    pub snapshots: Vec<Vsnapshot>,
+    /// VMs excluded by classification, per kind
+    pub excluded: BTreeMap<VmKind, usize>,
+    /// Files, sheets and rows skipped in lenient mode
+    pub warnings: Vec<Diagnostic>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -20,5 +79,12 @@ pub struct Datacenter {
     pub name: String,
     pub cluster: String,
     pub vm_count: usize,
-    pub capacity: f64,
+    pub capacity: Capacity,
+    /// VSE workload type, "VM" for the RVTools data
+    pub workload_type: String,
+    pub large_block: bool,
+    /// Retention pattern overriding the global --retention
+    pub retention: Option<String>,
+    /// Site the backups are copied to
+    pub copy_site: Option<String>,
 }
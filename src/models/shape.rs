@@ -0,0 +1,73 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::rvtools::Vinfo;
+
+/// Inventory choices made in the TUI, replayed with --shape-config
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ShapeConfig {
+    #[serde(default)]
+    pub excluded_datacenters: Vec<String>,
+    #[serde(default)]
+    pub excluded_clusters: Vec<ClusterRef>,
+    #[serde(default)]
+    pub excluded_vms: Vec<String>,
+    #[serde(default)]
+    pub assignments: Vec<ClusterAssignment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ClusterRef {
+    pub datacenter: String,
+    pub cluster: String,
+}
+
+/// Site and retention overrides for a vInfo cluster
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ClusterAssignment {
+    pub datacenter: String,
+    pub cluster: String,
+    #[serde(default)]
+    pub site: Option<String>,
+    /// Retention pattern, e.g. 30D1W1M1Y
+    #[serde(default)]
+    pub retention: Option<String>,
+}
+
+impl ShapeConfig {
+    pub fn is_excluded(&self, vm: &Vinfo) -> bool {
+        self.excluded_datacenters.contains(&vm.datacenter)
+            || self.is_cluster_excluded(&vm.datacenter, &vm.cluster)
+            || self.excluded_vms.contains(&vm.vm_name)
+    }
+
+    pub fn is_cluster_excluded(&self, datacenter: &str, cluster: &str) -> bool {
+        self.excluded_clusters
+            .iter()
+            .any(|x| x.datacenter == datacenter && x.cluster == cluster)
+    }
+
+    pub fn assignment(&self, datacenter: &str, cluster: &str) -> Option<&ClusterAssignment> {
+        self.assignments
+            .iter()
+            .find(|x| x.datacenter == datacenter && x.cluster == cluster)
+    }
+
+    pub fn assigned_site(&self, vm: &Vinfo) -> Option<&str> {
+        self.assignment(&vm.datacenter, &vm.cluster)
+            .and_then(|x| x.site.as_deref())
+    }
+
+    /// The assigned site, the datacenter otherwise
+    pub fn site_for<'a>(&'a self, vm: &'a Vinfo) -> &'a str {
+        self.assigned_site(vm).unwrap_or(&vm.datacenter)
+    }
+
+    pub fn retention_for(&self, vm: &Vinfo) -> Option<&str> {
+        self.assignment(&vm.datacenter, &vm.cluster)
+            .and_then(|x| x.retention.as_deref())
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Sub},
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Capacity units, decimal (GB/TB) and binary (GiB/TiB)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Unit {
+    #[value(name = "MB")]
+    Mb,
+    #[value(name = "MiB")]
+    Mib,
+    #[value(name = "GB")]
+    Gb,
+    #[value(name = "GiB")]
+    Gib,
+    #[value(name = "TB")]
+    Tb,
+    #[value(name = "TiB")]
+    Tib,
+}
+
+impl Unit {
+    pub fn bytes(&self) -> f64 {
+        match self {
+            Unit::Mb => 1e6,
+            Unit::Mib => 1024_f64.powi(2),
+            Unit::Gb => 1e9,
+            Unit::Gib => 1024_f64.powi(3),
+            Unit::Tb => 1e12,
+            Unit::Tib => 1024_f64.powi(4),
+        }
+    }
+
+    /// The per-VM unit to pair with a site level unit, e.g. TiB -> GiB
+    pub fn vm_unit(&self) -> Unit {
+        match self {
+            Unit::Mb | Unit::Gb | Unit::Tb => Unit::Gb,
+            Unit::Mib | Unit::Gib | Unit::Tib => Unit::Gib,
+        }
+    }
+
+    /// Unit of an RVTools capacity header, e.g. "In Use MiB"
+    ///
+    /// RVTools always reported binary units, the pre 4.1.2 "MB" headers were renamed to
+    /// "MiB" without changing the values, so they are read as MiB.
+    pub fn from_header(header: &str) -> Unit {
+        let header = header.to_lowercase();
+
+        if header.contains("tib") || header.contains("tb") {
+            Unit::Tib
+        } else if header.contains("gib") || header.contains("gb") {
+            Unit::Gib
+        } else {
+            Unit::Mib
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Unit::Mb => "MB",
+            Unit::Mib => "MiB",
+            Unit::Gb => "GB",
+            Unit::Gib => "GiB",
+            Unit::Tb => "TB",
+            Unit::Tib => "TiB",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Decimal separator of numbers stored as text, e.g. "1,024.5" or "1.024,5"
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DecimalSeparator {
+    // the last '.' or ',' is the decimal separator, a lone ',' followed by 3 digits groups thousands
+    #[default]
+    Auto,
+    Dot,
+    Comma,
+}
+
+/// A capacity held in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct Capacity(f64);
+
+impl Capacity {
+    pub fn new(value: f64, unit: Unit) -> Self {
+        Capacity(value * unit.bytes())
+    }
+
+    pub fn to(&self, unit: Unit) -> f64 {
+        self.0 / unit.bytes()
+    }
+
+    pub fn min(self, other: Capacity) -> Capacity {
+        Capacity(self.0.min(other.0))
+    }
+}
+
+impl Add for Capacity {
+    type Output = Capacity;
+
+    fn add(self, rhs: Capacity) -> Capacity {
+        Capacity(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Capacity {
+    fn add_assign(&mut self, rhs: Capacity) {
+        self.0 += rhs.0;
+    }
+}
+
+/// Saturates at zero, a capacity can't be negative
+impl Sub for Capacity {
+    type Output = Capacity;
+
+    fn sub(self, rhs: Capacity) -> Capacity {
+        Capacity((self.0 - rhs.0).max(0.0))
+    }
+}
+
+impl Sum for Capacity {
+    fn sum<I: Iterator<Item = Capacity>>(iter: I) -> Capacity {
+        iter.fold(Capacity::default(), |acc, x| acc + x)
+    }
+}
@@ -0,0 +1,44 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::{
+    helpers::load_records,
+    models::{
+        nas::NasShare,
+        rvtools::Vinfo,
+        units::{Capacity, Unit},
+    },
+};
+
+/// Loads NAS shares from a CSV (name,site,size_gb,files,copy_site) or JSON file
+pub fn load(path: &Path) -> Result<Vec<NasShare>> {
+    load_records(path)
+}
+
+/// One share per file server VM, the file count is estimated from the used capacity
+pub fn from_vms(
+    vms: &[Vinfo],
+    files_per_gb: f64,
+    site_for: impl Fn(&Vinfo) -> String,
+) -> Vec<NasShare> {
+    vms.iter()
+        .map(|x| {
+            let size_gb = x.capacity.to(Unit::Gb);
+            NasShare {
+                name: x.vm_name.to_string(),
+                site: site_for(x),
+                size_gb,
+                files: (size_gb * files_per_gb).round() as i64,
+                copy_site: None,
+            }
+        })
+        .collect()
+}
+
+pub fn total_capacity(shares: &[NasShare]) -> Capacity {
+    shares
+        .iter()
+        .map(|x| Capacity::new(x.size_gb, Unit::Gb))
+        .sum()
+}
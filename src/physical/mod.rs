@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use anyhow::Result;
+use itertools::Itertools;
+
+use crate::{
+    helpers::load_records,
+    models::{
+        physical::PhysicalServer,
+        rvtools::Datacenter,
+        units::{Capacity, Unit},
+    },
+};
+
+/// Loads physical servers from a CSV (name,site,used_gb,os,type) or JSON file
+pub fn load(path: &Path) -> Result<Vec<PhysicalServer>> {
+    let mut servers: Vec<PhysicalServer> = load_records(path)?;
+
+    // An empty CSV type cell is read as an empty string rather than the serde default
+    servers
+        .iter_mut()
+        .filter(|x| x.workload_type.is_empty())
+        .for_each(|x| x.workload_type = "Agent".to_string());
+
+    Ok(servers)
+}
+
+/// Groups the servers into one workload per site, OS and type
+pub fn to_datacenters(servers: &[PhysicalServer]) -> Vec<Datacenter> {
+    servers
+        .iter()
+        .sorted_by_key(|x| (&x.site, &x.os, &x.workload_type))
+        .group_by(|x| (&x.site, &x.os, &x.workload_type))
+        .into_iter()
+        .map(|((site, os, workload_type), group)| {
+            let mut capacity = Capacity::default();
+            let mut vm_count = 0;
+            group.for_each(|x| {
+                capacity += Capacity::new(x.used_gb, Unit::Gb);
+                vm_count += 1;
+            });
+
+            Datacenter {
+                name: site.to_string(),
+                cluster: format!("Physical {}", os),
+                vm_count,
+                capacity,
+                workload_type: workload_type.to_string(),
+                large_block: false,
+                retention: None,
+                copy_site: None,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,497 @@
+use std::{
+    collections::BTreeMap,
+    io::{Read, Seek},
+};
+
+use anyhow::Result;
+use itertools::Itertools;
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::{
+    bands::SizeBands,
+    excel::get_excel_from,
+    growth::GrowthPolicy,
+    helpers::load_json,
+    models::{
+        cli::Cli,
+        errors::Diagnostic,
+        nas::NasShare,
+        new_model::{Mapper, NewVse},
+        rvtools::{Datacenter, RvtoolsData, Vinfo, VmKind, Vpartition},
+        shape::ShapeConfig,
+        units::Capacity,
+    },
+    nas, physical, sitemap,
+    snapshot::{self, VmSnapshots},
+    vse::{self, vse_construct, IdGenerator, IdMap},
+    window::WindowPolicy,
+};
+
+/// The options that turn the inventory into workloads, read once from the CLI
+pub struct Settings {
+    pub windows: WindowPolicy,
+    pub size_bands: Option<SizeBands>,
+    pub growth: GrowthPolicy,
+    pub dc_map: Option<Vec<Mapper>>,
+    pub shape: ShapeConfig,
+    /// Ids of a previous run, from --id-map
+    pub ids: IdMap,
+}
+
+impl Settings {
+    pub fn from_cli(cli: &Cli) -> Result<Self> {
+        let dc_map = match &cli.dc_site_map {
+            Some(path) => Some(sitemap::load(path)?),
+            None => None,
+        };
+
+        // The TUI starts from an empty config when the file doesn't exist yet
+        let shape = match &cli.shape_config {
+            Some(path) if path.exists() || !cli.tui => load_json(path)?,
+            _ => ShapeConfig::default(),
+        };
+
+        let ids = match &cli.id_map {
+            Some(path) => vse::load_map(path)?,
+            None => IdMap::new(),
+        };
+
+        Ok(Self {
+            windows: WindowPolicy::from_cli(cli)?,
+            size_bands: SizeBands::from_cli(cli)?,
+            growth: GrowthPolicy::from_cli(cli)?,
+            dc_map,
+            shape,
+            ids,
+        })
+    }
+
+    /// The workloads for the VMs left after the `shape` exclusions, plus the physical servers
+    pub fn workloads(
+        &self,
+        cli: &Cli,
+        inventory: &Inventory,
+        vms: &[Vinfo],
+        shape: &ShapeConfig,
+    ) -> Vec<Datacenter> {
+        let mut datacenters = self.vm_workloads(cli, vms, shape);
+        datacenters.extend(inventory.physical_workloads.iter().cloned());
+        datacenters
+    }
+
+    /// The workloads for the VMs left after the `shape` exclusions
+    pub fn vm_workloads(&self, cli: &Cli, vms: &[Vinfo], shape: &ShapeConfig) -> Vec<Datacenter> {
+        let vms = vms
+            .iter()
+            .filter(|x| !shape.is_excluded(x))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        aggregate(&vms, cli, &self.dc_map, &self.size_bands, shape)
+    }
+
+    pub fn vse(
+        &self,
+        cli: &Cli,
+        nas_shares: &[NasShare],
+        datacenters: &[Datacenter],
+    ) -> Result<NewVse> {
+        self.vse_with_ids(cli, nas_shares, datacenters)
+            .map(|(vse, _)| vse)
+    }
+
+    /// The VSE and the ids of all its objects, to keep them for the next run
+    pub fn vse_with_ids(
+        &self,
+        cli: &Cli,
+        nas_shares: &[NasShare],
+        datacenters: &[Datacenter],
+    ) -> Result<(NewVse, IdMap)> {
+        let mut ids = IdGenerator::new(self.ids.clone());
+
+        let vse = vse_construct(
+            site_names(datacenters),
+            datacenters,
+            cli.retention.clone(),
+            &self.windows,
+            nas_shares,
+            cli.nas_retention.as_deref(),
+            &self.growth,
+            &mut ids,
+        )?;
+
+        Ok((vse, ids.into_map()))
+    }
+}
+
+/// The loaded VMs with the vPartition capacity applied, and the other workload sources
+pub struct Inventory {
+    pub vms: Vec<Vinfo>,
+    pub snapshots: Vec<VmSnapshots>,
+    /// VMs excluded by classification, per kind
+    pub excluded: BTreeMap<VmKind, usize>,
+    /// Files, sheets and rows skipped while loading
+    pub warnings: Vec<Diagnostic>,
+    pub nas_shares: Vec<NasShare>,
+    pub physical_count: usize,
+    pub physical_workloads: Vec<Datacenter>,
+}
+
+/// A VSE file and the data it was built from
+pub struct Conversion {
+    pub settings: Settings,
+    pub inventory: Inventory,
+    pub datacenters: Vec<Datacenter>,
+    pub vse: NewVse,
+}
+
+/// Converts RVTools workbooks read from `sources` into a VSE file, using the CLI options
+/// without printing anything
+pub fn convert<RS: Read + Seek + Send>(
+    sources: Vec<(String, RS)>,
+    cli: &Cli,
+) -> Result<Conversion> {
+    let settings = Settings::from_cli(cli)?;
+    let data = get_excel_from(sources, cli)?;
+    let inventory = prepare(cli, data, &settings)?;
+
+    let datacenters = settings.workloads(cli, &inventory, &inventory.vms, &settings.shape);
+    let vse = settings.vse(cli, &inventory.nas_shares, &datacenters)?;
+
+    Ok(Conversion {
+        settings,
+        inventory,
+        datacenters,
+        vse,
+    })
+}
+
+/// Applies the snapshot, vPartition and NAS options to the RVTools data and loads the
+/// physical servers
+pub fn prepare(cli: &Cli, data: RvtoolsData, settings: &Settings) -> Result<Inventory> {
+    let RvtoolsData {
+        info: mut info_vec,
+        partitions: part_vec,
+        snapshots: snap_vec,
+        excluded,
+        warnings,
+    } = data;
+
+    let snapshots = snapshot::summarize(&snap_vec);
+
+    if cli.subtract_snapshots {
+        // VM names repeat across vCenters, so the snapshots are matched within their file
+        let snap_map: HashMap<(&String, &String), Capacity> = snapshots
+            .iter()
+            .map(|x| ((&x.source, &x.vm_name), x.capacity))
+            .collect();
+
+        info_vec.iter_mut().for_each(|x| {
+            if let Some(snap_cap) = snap_map.get(&(&x.source, &x.vm_name)) {
+                x.capacity = x.capacity - *snap_cap;
+            }
+        });
+    }
+
+    let grouped: Vec<Vpartition> = part_vec
+        .into_iter()
+        .sorted_by_key(|x| x.vm_name.clone())
+        .group_by(|x| x.vm_name.clone())
+        .into_iter()
+        .map(|(name, group)| {
+            let total = group.map(|x| x.capacity).sum();
+            Vpartition {
+                vm_name: name,
+                capacity: total,
+            }
+        })
+        .collect();
+
+    let mut combined: Vec<Vinfo> = Vec::new();
+    let mut group_map = HashMap::new();
+
+    for (i, j) in grouped.iter().enumerate() {
+        group_map.insert(j.vm_name.clone(), i);
+    }
+
+    if !cli.do_not_use_vpartition {
+        for i in &info_vec {
+            if let Some(&j_idx) = group_map.get(&i.vm_name) {
+                let j = &grouped[j_idx];
+                let low_cap = i.capacity.min(j.capacity);
+
+                let new_st = Vinfo {
+                    vm_name: i.vm_name.clone(),
+                    datacenter: i.datacenter.clone(),
+                    cluster: i.cluster.clone(),
+                    capacity: low_cap,
+                    powerstate: i.powerstate.clone(),
+                    kind: i.kind,
+                    group: i.group.clone(),
+                    source: i.source.clone(),
+                };
+                combined.push(new_st);
+            } else {
+                combined.push(i.clone());
+            }
+        }
+    } else {
+        combined = info_vec.clone()
+    }
+
+    let mut nas_shares = match &cli.nas {
+        Some(nas_file) => nas::load(nas_file)?,
+        None => vec![],
+    };
+
+    if let Some(pattern) = &cli.nas_vm_pattern {
+        let re = Regex::new(pattern)?;
+
+        let (file_servers, vms): (Vec<Vinfo>, Vec<Vinfo>) =
+            combined.into_iter().partition(|x| re.is_match(&x.vm_name));
+        combined = vms;
+
+        nas_shares.extend(nas::from_vms(&file_servers, cli.nas_files_per_gb, |vm| {
+            // file servers outside the DC map still get a NAS share in their datacenter
+            site_for_vm(vm, cli, &settings.dc_map, &settings.shape)
+                .unwrap_or_else(|| vm.datacenter.to_string())
+        }));
+    }
+
+    let physical_servers = match &cli.physical {
+        Some(physical_file) => physical::load(physical_file)?,
+        None => vec![],
+    };
+    let physical_count = physical_servers.len();
+    let physical_workloads = physical::to_datacenters(&physical_servers);
+
+    Ok(Inventory {
+        vms: combined,
+        snapshots,
+        excluded,
+        warnings,
+        nas_shares,
+        physical_count,
+        physical_workloads,
+    })
+}
+
+/// Groups the VMs into workloads per site and cluster, applying the flatten, DC map and
+/// shape config site and retention choices
+pub fn aggregate(
+    combined: &[Vinfo],
+    cli: &Cli,
+    dc_map: &Option<Vec<Mapper>>,
+    size_bands: &Option<SizeBands>,
+    shape: &ShapeConfig,
+) -> Vec<Datacenter> {
+    if let Some(dc_map) = dc_map {
+        return map_workloads(combined, dc_map, size_bands, shape);
+    }
+
+    let mut datacenters: Vec<Datacenter> = Vec::new();
+
+    // Flattens the DC results into single clusters
+    let band = |x: &Vinfo| size_bands.as_ref().map(|b| b.band(x.capacity));
+
+    if cli.flatten && !cli.flatten_site {
+        // one site and cluster, still split on the size bands and retentions
+        combined
+            .iter()
+            .sorted_by_key(|s| (band(s), shape.retention_for(s)))
+            .group_by(|s| (band(s), shape.retention_for(s)))
+            .into_iter()
+            .for_each(|(key, group)| {
+                let mut cap = Capacity::default();
+                let mut vm_count = 0;
+                group.for_each(|x| {
+                    cap += x.capacity;
+                    vm_count += 1;
+                });
+
+                datacenters.push(Datacenter {
+                    name: "DC1".to_string(),
+                    cluster: band_name("Cluster1".to_string(), size_bands, key.0),
+                    vm_count,
+                    capacity: cap,
+                    workload_type: "VM".to_string(),
+                    large_block: is_large_band(size_bands, key.0),
+                    retention: key.1.map(str::to_string),
+                    copy_site: None,
+                })
+            });
+    } else if cli.flatten_site && !cli.flatten {
+        combined
+            .iter()
+            .sorted_by_key(|s| (shape.site_for(s), band(s), shape.retention_for(s)))
+            .group_by(|s| (shape.site_for(s), band(s), shape.retention_for(s)))
+            .into_iter()
+            .for_each(|(key, group)| {
+                let mut cap = Capacity::default();
+                let mut vm_count = 0;
+                group.for_each(|x| {
+                    cap += x.capacity;
+                    vm_count += 1;
+                });
+
+                datacenters.push(Datacenter {
+                    name: key.0.to_string(),
+                    cluster: band_name(format!("{}_cluster", key.0), size_bands, key.1),
+                    vm_count,
+                    capacity: cap,
+                    workload_type: "VM".to_string(),
+                    large_block: is_large_band(size_bands, key.1),
+                    retention: key.2.map(str::to_string),
+                    copy_site: None,
+                })
+            });
+    } else {
+        combined
+            .iter()
+            .sorted_by_key(|s| {
+                (
+                    shape.site_for(s),
+                    s.group_key(),
+                    band(s),
+                    shape.retention_for(s),
+                )
+            })
+            .group_by(|s| {
+                (
+                    shape.site_for(s),
+                    s.group_key(),
+                    band(s),
+                    shape.retention_for(s),
+                )
+            })
+            .into_iter()
+            .for_each(|(key, group)| {
+                let mut cap = Capacity::default();
+                let mut vm_count = 0;
+                group.for_each(|x| {
+                    cap += x.capacity;
+                    vm_count += 1;
+                });
+
+                datacenters.push(Datacenter {
+                    name: key.0.to_string(),
+                    cluster: band_name(key.1.to_string(), size_bands, key.2),
+                    vm_count,
+                    capacity: cap,
+                    workload_type: "VM".to_string(),
+                    large_block: is_large_band(size_bands, key.2),
+                    retention: key.3.map(str::to_string),
+                    copy_site: None,
+                })
+            });
+    }
+
+    datacenters
+}
+
+/// Groups the VMs into a workload per DC map site and size band, each VM is matched on its
+/// own datacenter and cluster and the VMs outside the map are left out
+///
+/// CSV and YAML maps have a row per datacenter and cluster, the rows of a site with the same
+/// retention and copy site add up to one workload. A site or retention assigned to the
+/// cluster in the shape config wins over the DC map, and the copy site only applies to the
+/// VMs that stay in the map's site.
+fn map_workloads(
+    combined: &[Vinfo],
+    dc_map: &[Mapper],
+    size_bands: &Option<SizeBands>,
+    shape: &ShapeConfig,
+) -> Vec<Datacenter> {
+    let key = |x: &Vinfo| {
+        let group = sitemap::group_for(dc_map, &x.datacenter, &x.cluster);
+        let site = shape
+            .assigned_site(x)
+            .or(group.map(|g| g.group_name.as_str()))?;
+        let retention = shape
+            .retention_for(x)
+            .or(group.and_then(|g| g.retention.as_deref()));
+        let copy_site = group
+            .filter(|g| g.group_name == site)
+            .and_then(|g| g.copy_site.as_deref());
+
+        Some((
+            site,
+            size_bands.as_ref().map(|b| b.band(x.capacity)),
+            retention,
+            copy_site,
+        ))
+    };
+
+    combined
+        .iter()
+        .filter_map(|x| key(x).map(|key| (key, x)))
+        .sorted_by_key(|(key, _)| *key)
+        .group_by(|(key, _)| *key)
+        .into_iter()
+        .map(|((site, band, retention, copy_site), group)| {
+            let mut capacity = Capacity::default();
+            let mut vm_count = 0;
+            group.for_each(|(_, x)| {
+                capacity += x.capacity;
+                vm_count += 1;
+            });
+
+            Datacenter {
+                name: site.to_string(),
+                cluster: band_name(format!("{}_cluster", site), size_bands, band),
+                vm_count,
+                capacity,
+                workload_type: "VM".to_string(),
+                large_block: is_large_band(size_bands, band),
+                retention: retention.map(str::to_string),
+                copy_site: copy_site.map(str::to_string),
+            }
+        })
+        .collect()
+}
+
+pub fn site_names(datacenters: &[Datacenter]) -> Vec<String> {
+    datacenters
+        .iter()
+        .map(|x| x.name.to_string())
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+/// The site a VM ends up in after any flattening, DC mapping or shape config assignment,
+/// matched the same way as its workload, `None` for the VMs the DC map leaves out
+pub fn site_for_vm(
+    vm: &Vinfo,
+    cli: &Cli,
+    dc_map: &Option<Vec<Mapper>>,
+    shape: &ShapeConfig,
+) -> Option<String> {
+    if let Some(dc_map) = dc_map {
+        shape
+            .assigned_site(vm)
+            .or(sitemap::group_for(dc_map, &vm.datacenter, &vm.cluster)
+                .map(|x| x.group_name.as_str()))
+            .map(str::to_string)
+    } else if cli.flatten && !cli.flatten_site {
+        Some("DC1".to_string())
+    } else {
+        Some(shape.site_for(vm).to_string())
+    }
+}
+
+/// Adds the size band to a workload name when the VMs are split into bands
+fn band_name(name: String, size_bands: &Option<SizeBands>, band: Option<usize>) -> String {
+    match (size_bands, band) {
+        (Some(bands), Some(band)) => format!("{}_{}", name, bands.label(band)),
+        _ => name,
+    }
+}
+
+fn is_large_band(size_bands: &Option<SizeBands>, band: Option<usize>) -> bool {
+    match (size_bands, band) {
+        (Some(bands), Some(band)) => bands.is_large(band),
+        _ => false,
+    }
+}
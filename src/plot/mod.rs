@@ -1,6 +1,8 @@
 use termplot::*;
 
-pub fn plot_data(data: Vec<f64>, site_names: Vec<String>) {
+use crate::models::units::Unit;
+
+pub fn plot_data(data: Vec<f64>, site_names: Vec<String>, unit: Unit) {
     let mut plot = Plot::default();
 
     let item_count = data.len() as f64;
@@ -11,8 +13,8 @@ pub fn plot_data(data: Vec<f64>, site_names: Vec<String>) {
     plot.set_domain(Domain(0.0..item_count))
         .set_codomain(Domain(0.0..*max_value))
         .set_title("Capacity")
-        .set_x_label(format!("{}", site_names.join(", ")).as_str())
-        .set_y_label("y: Capacity (TB)")
+        .set_x_label(site_names.join(", ").as_str())
+        .set_y_label(format!("y: Capacity ({})", unit).as_str())
         .set_size(Size::new(150, 75))
         .add_plot(Box::new(plot::Bars::new(data)));
 
@@ -0,0 +1,245 @@
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    growth::ForecastRow,
+    models::{rvtools::Datacenter, units::Unit},
+    stats::VmStats,
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SummaryRow {
+    pub site: String,
+    pub cluster: String,
+    pub vm_count: usize,
+    pub capacity: f64,
+}
+
+/// Summary data written by --report-file, capacities are in `unit`
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub unit: String,
+    pub summary: Vec<SummaryRow>,
+    pub forecast: Vec<ForecastRow>,
+    /// VM sizes are in the VM unit of `unit`, e.g. GiB for TiB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vm_stats: Option<VmStats>,
+}
+
+impl Report {
+    pub fn new(unit: Unit) -> Self {
+        Self {
+            unit: unit.to_string(),
+            summary: vec![],
+            forecast: vec![],
+            vm_stats: None,
+        }
+    }
+
+    pub fn from_datacenters(
+        datacenters: &[Datacenter],
+        forecast: Vec<ForecastRow>,
+        unit: Unit,
+    ) -> Self {
+        Self {
+            summary: datacenters
+                .iter()
+                .map(|x| SummaryRow {
+                    site: x.name.to_string(),
+                    cluster: x.cluster.to_string(),
+                    vm_count: x.vm_count,
+                    capacity: x.capacity.to(unit),
+                })
+                .collect(),
+            forecast,
+            ..Self::new(unit)
+        }
+    }
+}
+
+/// Writes the report as HTML for a .html/.htm file, JSON otherwise
+pub fn write(path: &Path, report: &Report) -> Result<()> {
+    let is_html = path
+        .extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| x.eq_ignore_ascii_case("html") || x.eq_ignore_ascii_case("htm"));
+
+    let content = if is_html {
+        to_html(report)
+    } else {
+        serde_json::to_string_pretty(report)?
+    };
+
+    fs::write(path, content)?;
+    println!("Report written to: {}", path.display());
+
+    Ok(())
+}
+
+fn to_html(report: &Report) -> String {
+    let unit = &report.unit;
+
+    let summary = html_table(
+        "Summary",
+        &[
+            "Site".to_string(),
+            "Cluster".to_string(),
+            "VM Count".to_string(),
+            format!("Capacity ({})", unit),
+        ],
+        report
+            .summary
+            .iter()
+            .map(|x| {
+                vec![
+                    x.site.to_string(),
+                    x.cluster.to_string(),
+                    x.vm_count.to_string(),
+                    format!("{:.2}", x.capacity),
+                ]
+            })
+            .collect(),
+    );
+
+    let year_count = report
+        .forecast
+        .iter()
+        .map(|x| x.years.len())
+        .max()
+        .unwrap_or(0);
+    let mut forecast_header = vec![
+        "Site".to_string(),
+        "Cluster".to_string(),
+        "Growth (%)".to_string(),
+        format!("Now ({})", unit),
+    ];
+    forecast_header.extend((1..year_count).map(|x| format!("Year {} ({})", x, unit)));
+
+    let forecast = html_table(
+        "Growth forecast",
+        &forecast_header,
+        report
+            .forecast
+            .iter()
+            .map(|x| {
+                let mut row = vec![
+                    x.site.to_string(),
+                    x.cluster.to_string(),
+                    format!("{:.1}", x.growth_rate),
+                ];
+                row.extend(x.years.iter().map(|y| format!("{:.2}", y)));
+                row
+            })
+            .collect(),
+    );
+
+    let vm_stats = report
+        .vm_stats
+        .as_ref()
+        .map(html_vm_stats)
+        .unwrap_or_default();
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>rvtools2vse report</title>\n\
+         <style>body{{font-family:sans-serif}}table{{border-collapse:collapse;margin-bottom:2em}}\
+         th,td{{border:1px solid #ccc;padding:4px 8px;text-align:right}}\
+         th:first-child,td:first-child,th:nth-child(2),td:nth-child(2){{text-align:left}}</style>\n\
+         </head>\n<body>\n<h1>rvtools2vse report</h1>\n{}{}{}</body>\n</html>\n",
+        summary, vm_stats, forecast
+    )
+}
+
+fn html_vm_stats(stats: &VmStats) -> String {
+    let unit = &stats.unit;
+
+    let mut header = vec![
+        "Datacenter".to_string(),
+        "Cluster".to_string(),
+        "VM Count".to_string(),
+        format!("Mean ({})", unit),
+        format!("Median ({})", unit),
+        format!("P90 ({})", unit),
+        format!("P99 ({})", unit),
+        format!("Max ({})", unit),
+    ];
+    header.extend(stats.thresholds_gib.iter().map(|x| format!("> {} GiB", x)));
+
+    let sizes = html_table(
+        "VM sizes",
+        &header,
+        stats
+            .clusters
+            .iter()
+            .chain(std::iter::once(&stats.global))
+            .map(|x| {
+                let mut row = vec![
+                    x.datacenter.to_string(),
+                    x.cluster.to_string(),
+                    x.vm_count.to_string(),
+                    format!("{:.2}", x.mean),
+                    format!("{:.2}", x.median),
+                    format!("{:.2}", x.p90),
+                    format!("{:.2}", x.p99),
+                    format!("{:.2}", x.max),
+                ];
+                row.extend(x.above.iter().map(|count| count.to_string()));
+                row
+            })
+            .collect(),
+    );
+
+    let largest = html_table(
+        "Largest VMs",
+        &[
+            "VM Name".to_string(),
+            "Datacenter".to_string(),
+            "Cluster".to_string(),
+            format!("Capacity ({})", unit),
+        ],
+        stats
+            .largest
+            .iter()
+            .map(|x| {
+                vec![
+                    x.vm_name.to_string(),
+                    x.datacenter.to_string(),
+                    x.cluster.to_string(),
+                    format!("{:.2}", x.capacity),
+                ]
+            })
+            .collect(),
+    );
+
+    sizes + &largest
+}
+
+fn html_table(title: &str, header: &[String], rows: Vec<Vec<String>>) -> String {
+    if rows.is_empty() {
+        return String::new();
+    }
+
+    let mut html = format!("<h2>{}</h2>\n<table>\n<tr>", escape(title));
+    header
+        .iter()
+        .for_each(|x| html.push_str(&format!("<th>{}</th>", escape(x))));
+    html.push_str("</tr>\n");
+
+    rows.iter().for_each(|row| {
+        html.push_str("<tr>");
+        row.iter()
+            .for_each(|x| html.push_str(&format!("<td>{}</td>", escape(x))));
+        html.push_str("</tr>\n");
+    });
+
+    html.push_str("</table>\n");
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,66 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use schemars::{schema::RootSchema, schema_for};
+
+use crate::{
+    models::{
+        nas::NasShare,
+        new_model::{Mapper, NewVse},
+        physical::PhysicalServer,
+        shape::ShapeConfig,
+    },
+    vse::IdMap,
+};
+
+/// JSON Schemas of the files the tool reads and writes, by file name
+pub fn schemas() -> Vec<(String, RootSchema)> {
+    vec![
+        (
+            "vse.schema.json".to_string(),
+            titled(schema_for!(NewVse), "VSE file"),
+        ),
+        (
+            "dc_site_map.schema.json".to_string(),
+            titled(schema_for!(Vec<Mapper>), "DC site map (--dc-site-map)"),
+        ),
+        (
+            "shape_config.schema.json".to_string(),
+            titled(schema_for!(ShapeConfig), "Shape config (--shape-config)"),
+        ),
+        (
+            "id_map.schema.json".to_string(),
+            titled(schema_for!(IdMap), "VSE id map (--id-map)"),
+        ),
+        (
+            "physical.schema.json".to_string(),
+            titled(
+                schema_for!(Vec<PhysicalServer>),
+                "Physical servers (--physical)",
+            ),
+        ),
+        (
+            "nas.schema.json".to_string(),
+            titled(schema_for!(Vec<NasShare>), "NAS shares (--nas)"),
+        ),
+    ]
+}
+
+/// Writes all the schemas to `dir`, creating it when needed
+pub fn write(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("Creating {:?}", dir))?;
+
+    for (name, schema) in schemas() {
+        let path = dir.join(name);
+        fs::write(&path, serde_json::to_string_pretty(&schema)?)
+            .with_context(|| format!("Writing {:?}", path))?;
+        println!("{} written", path.display());
+    }
+
+    Ok(())
+}
+
+fn titled(mut schema: RootSchema, title: &str) -> RootSchema {
+    schema.schema.metadata().title = Some(title.to_string());
+    schema
+}
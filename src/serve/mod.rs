@@ -0,0 +1,226 @@
+use std::{
+    collections::BTreeMap,
+    io::{Cursor, Read},
+};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use itertools::Itertools;
+use serde::Serialize;
+use serde_json::Value;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    growth,
+    models::{cli::Cli, errors::Diagnostic, new_model::NewVse, rvtools::VmKind, units::Capacity},
+    pipeline::{self, Conversion},
+    report::Report,
+    stats::VmStats,
+};
+
+/// Largest accepted upload, RVTools exports are well below this
+const MAX_UPLOAD_BYTES: usize = 512 * 1024 * 1024;
+
+const FORM: &str = include_str!("form.html");
+
+/// The conversion options a client can set, options that name server-side files, print to
+/// the server console or switch to another mode are left out
+const ALLOWED_OPTIONS: &[&str] = &[
+    "strict",
+    "decimal_separator",
+    "include_powered_off",
+    "include_templates",
+    "include_srm_placeholders",
+    "include_replicas",
+    "replica_suffix",
+    "standalone_cluster",
+    "missing_datacenter",
+    "retention",
+    "dc_include",
+    "cluster_include",
+    "dc_exclude",
+    "cluster_exclude",
+    "vm_exclude",
+    "legacy",
+    "do_not_use_vpartition",
+    "nas_vm_pattern",
+    "nas_files_per_gb",
+    "nas_retention",
+    "project_length",
+    "growth_rate",
+    "site_growth",
+    "unit",
+    "size_thresholds",
+    "top_vms",
+    "group_by",
+    "size_bands",
+    "band_limits",
+    "large_block_gib",
+    "flatten",
+    "flatten_site",
+    "anonymize",
+    "backup_window",
+    "site_window",
+    "class_window",
+    "max_throughput",
+    "subtract_snapshots",
+];
+
+/// Body of a successful POST /convert
+#[derive(Debug, Serialize)]
+struct ConvertResponse {
+    vse: NewVse,
+    summary: Report,
+    total_vms: usize,
+    total_capacity: f64,
+    excluded: BTreeMap<VmKind, usize>,
+    warnings: Vec<Diagnostic>,
+}
+
+/// Serves the HTML form on / and the conversion on POST /convert until the process is stopped
+pub fn run(addr: &str) -> Result<()> {
+    let server = Server::http(addr).map_err(|e| anyhow!("Could not listen on {}: {}", addr, e))?;
+
+    println!("Serving on http://{}", server.server_addr());
+
+    for mut request in server.incoming_requests() {
+        let path = request
+            .url()
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        let (status, content_type, body) = match (request.method(), path.as_str()) {
+            (Method::Get, "/") => (200, "text/html; charset=utf-8", FORM.to_string()),
+            (Method::Post, "/convert") => match convert(&mut request) {
+                Ok(response) => (
+                    200,
+                    "application/json",
+                    serde_json::to_string_pretty(&response)?,
+                ),
+                Err(e) => (400, "application/json", error_body(&e.to_string())),
+            },
+            _ => (404, "application/json", error_body("Not found")),
+        };
+
+        println!("{} {} - {}", request.method(), path, status);
+
+        let header = Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes())
+            .map_err(|_| anyhow!("Invalid content type"))?;
+        let response = Response::from_string(body)
+            .with_status_code(status)
+            .with_header(header);
+
+        if let Err(e) = request.respond(response) {
+            println!("Could not send the response: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts the uploaded xlsx body with the options from the X-Options header
+fn convert(request: &mut Request) -> Result<ConvertResponse> {
+    let options = request
+        .headers()
+        .iter()
+        .find(|x| x.field.equiv("X-Options"))
+        .map(|x| x.value.as_str().to_string())
+        .unwrap_or_else(|| "{}".to_string());
+
+    let cli = Cli::try_parse_from(options_to_args(&options)?)?;
+
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_UPLOAD_BYTES as u64 + 1)
+        .read_to_end(&mut body)?;
+
+    if body.is_empty() {
+        return Err(anyhow!("The request body must be an RVTools xlsx file"));
+    }
+    if body.len() > MAX_UPLOAD_BYTES {
+        return Err(anyhow!(
+            "The upload is larger than {} bytes",
+            MAX_UPLOAD_BYTES
+        ));
+    }
+
+    let Conversion {
+        settings,
+        inventory,
+        datacenters,
+        vse,
+    } = pipeline::convert(vec![("upload".to_string(), Cursor::new(body))], &cli)?;
+
+    let forecast = growth::forecast(&datacenters, &settings.growth, cli.unit);
+    let total_capacity: Capacity = datacenters.iter().map(|x| x.capacity).sum();
+    let vms = inventory
+        .vms
+        .iter()
+        .filter(|x| !settings.shape.is_excluded(x))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let vm_stats = VmStats::from_vms(&vms, &cli)?;
+
+    Ok(ConvertResponse {
+        vse,
+        summary: Report {
+            vm_stats: Some(vm_stats),
+            ..Report::from_datacenters(&datacenters, forecast, cli.unit)
+        },
+        total_vms: vms.len(),
+        total_capacity: total_capacity.to(cli.unit),
+        excluded: inventory.excluded,
+        warnings: inventory.warnings,
+    })
+}
+
+/// Turns the options JSON into CLI arguments, e.g. {"size_bands": true, "unit": "GiB"}
+/// becomes --size-bands --unit GiB
+fn options_to_args(options: &str) -> Result<Vec<String>> {
+    let options: BTreeMap<String, Value> = serde_json::from_str(options)
+        .map_err(|e| anyhow!("Options must be a JSON object: {}", e))?;
+
+    let mut args = vec!["rvtools2vse".to_string()];
+
+    for (key, value) in options {
+        if !ALLOWED_OPTIONS.contains(&key.as_str()) {
+            return Err(anyhow!(
+                "Option {:?} is not available in serve mode, the options are: {}",
+                key,
+                ALLOWED_OPTIONS.join(", ")
+            ));
+        }
+
+        let flag = format!("--{}", key.replace('_', "-"));
+
+        match value {
+            Value::Bool(true) => args.push(flag),
+            Value::Bool(false) | Value::Null => {}
+            Value::Array(items) => {
+                args.push(flag);
+                args.push(items.iter().map(value_string).join(","));
+            }
+            value => {
+                args.push(flag);
+                args.push(value_string(&value));
+            }
+        }
+    }
+
+    Ok(args)
+}
+
+fn value_string(value: &Value) -> String {
+    match value {
+        Value::String(x) => x.to_string(),
+        x => x.to_string(),
+    }
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
@@ -0,0 +1,137 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use itertools::Itertools;
+
+use crate::{
+    helpers::{load_json, load_records, load_yaml},
+    models::{
+        new_model::{Mapper, SiteMapRow},
+        rvtools::Vinfo,
+    },
+    vse::validate_retention,
+};
+
+/// Loads a DC site map from a JSON, YAML or CSV file, by extension
+///
+/// A CSV file has a "datacenter,cluster,site,copy_site,retention" header, only datacenter and
+/// site need a value and other columns are ignored. JSON and YAML files are a list of groups.
+pub fn load(path: &Path) -> Result<Vec<Mapper>> {
+    let extension = path
+        .extension()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let mappers: Vec<Mapper> = match extension.as_str() {
+        "json" => load_json(path)?,
+        "yaml" | "yml" => load_yaml(path)?,
+        "csv" => load_records::<SiteMapRow>(path)
+            .with_context(|| format!("Reading {:?}", path))?
+            .into_iter()
+            .map(Mapper::from)
+            .collect(),
+        _ => {
+            return Err(anyhow!(
+                "DC site map must be .json, .yaml, .yml or .csv, got {:?}",
+                path
+            ))
+        }
+    };
+
+    for mapper in &mappers {
+        if let Some(retention) = &mapper.retention {
+            validate_retention(retention)
+                .map_err(|e| anyhow!("{:?}: site {}: {}", path, mapper.group_name, e))?;
+        }
+    }
+
+    if let Some((first, second, datacenter)) = overlap(&mappers) {
+        return Err(anyhow!(
+            "{:?}: datacenter {} is mapped to {} and {} with overlapping clusters, \
+             give each cluster one row",
+            path,
+            datacenter,
+            first.group_name,
+            second.group_name
+        ));
+    }
+
+    Ok(mappers)
+}
+
+/// The first two groups that both take VMs of a datacenter, either because one has no
+/// clusters and so takes the whole datacenter or because they share a cluster
+fn overlap(mappers: &[Mapper]) -> Option<(&Mapper, &Mapper, &str)> {
+    mappers.iter().tuple_combinations().find_map(|(a, b)| {
+        let datacenter = a.dc_names.iter().find(|x| b.dc_names.contains(x))?;
+        let clusters_overlap = match (&a.clusters, &b.clusters) {
+            (Some(a), Some(b)) => a.iter().any(|x| b.contains(x)),
+            _ => true,
+        };
+        clusters_overlap.then_some((a, b, datacenter.as_str()))
+    })
+}
+
+/// A DC site map with a group per datacenter name prefix, e.g. LON-DC1 and LON-DC2 in LON
+pub fn template(vms: &[Vinfo], with_clusters: bool) -> Vec<Mapper> {
+    vms.iter()
+        .map(|x| &x.datacenter)
+        .sorted()
+        .dedup()
+        .into_group_map_by(|x| name_prefix(x))
+        .into_iter()
+        .sorted_by(|a, b| a.0.cmp(&b.0))
+        .map(|(group_name, dc_names)| {
+            let clusters = with_clusters.then(|| {
+                vms.iter()
+                    .filter(|x| dc_names.contains(&&x.datacenter))
+                    .map(|x| x.cluster.to_string())
+                    .sorted()
+                    .dedup()
+                    .collect()
+            });
+
+            Mapper {
+                group_name,
+                dc_names: dc_names.into_iter().cloned().collect(),
+                clusters,
+                copy_site: None,
+                retention: None,
+            }
+        })
+        .collect()
+}
+
+/// The first group that takes the VMs of this vInfo datacenter and cluster
+pub fn group_for<'a>(dc_map: &'a [Mapper], datacenter: &str, cluster: &str) -> Option<&'a Mapper> {
+    dc_map.iter().find(|x| x.matches(datacenter, cluster))
+}
+
+/// Writes the template, an existing file is only replaced with `force`
+pub fn write_template(path: &Path, mappers: &[Mapper], force: bool) -> Result<()> {
+    if path.exists() && !force {
+        return Err(anyhow!(
+            "{} already exists, use --force to overwrite it",
+            path.display()
+        ));
+    }
+
+    fs::write(path, serde_json::to_string_pretty(mappers)?)
+        .with_context(|| format!("Writing {:?}", path))
+}
+
+/// The name up to the first separator or digit, e.g. "LON-DC1" -> "LON", "AMS2" -> "AMS"
+fn name_prefix(name: &str) -> String {
+    let prefix = name
+        .split(['-', '_', ' ', '.'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(|c: char| c.is_ascii_digit());
+
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        prefix.to_string()
+    }
+}
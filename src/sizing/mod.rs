@@ -0,0 +1,290 @@
+use comfy_table::{
+    modifiers::{UTF8_ROUND_CORNERS, UTF8_SOLID_INNER_BORDERS},
+    presets::UTF8_FULL,
+    Table,
+};
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::models::new_model::{DataProperty, NewVse, Retentions};
+
+/// Days of change captured between GFS restore points on block-clone storage
+const WEEK_DAYS: f64 = 7.0;
+const MONTH_DAYS: f64 = 30.0;
+const YEAR_DAYS: f64 = 365.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct YearCapacity {
+    pub year: i64,
+    pub source_tb: f64,
+    pub full_tb: f64,
+    pub incremental_tb: f64,
+    pub total_tb: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SiteEstimate {
+    pub site_id: String,
+    pub source_tb: f64,
+    pub years: Vec<YearCapacity>,
+}
+
+/// Restore points each workload with this retention keeps at the end of the project
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionPoints {
+    pub retention_id: String,
+    pub workloads: usize,
+    pub fulls: i64,
+    pub incrementals: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Estimate {
+    pub sites: Vec<SiteEstimate>,
+    pub retentions: Vec<RetentionPoints>,
+}
+
+struct SizingInput<'a> {
+    site_id: &'a str,
+    source_tb: f64,
+    data_property: Option<&'a DataProperty>,
+    retention: Option<&'a Retentions>,
+    repo_id: &'a str,
+}
+
+/// Approximates the performance tier repository capacity per site.
+///
+/// Forever forward incremental is assumed: one active full plus a daily incremental for
+/// each remaining simple restore point, with GFS points kept as additional fulls. On
+/// block-clone repositories (xfsRefs) a GFS full only consumes the blocks changed since
+/// the previous point, otherwise every full is counted at its reduced size.
+pub fn estimate(vse: &NewVse) -> Estimate {
+    let default_dp = vse.data_properties.iter().find(|x| x.default);
+    let default_rt = vse.retentions.iter().find(|x| x.default);
+
+    let vm_inputs = vse
+        .workloads
+        .iter()
+        .filter(|x| x.enabled)
+        .map(|x| SizingInput {
+            site_id: &x.site_id,
+            source_tb: x.source_tb,
+            data_property: find_data_property(vse, &x.data_property_id).or(default_dp),
+            retention: find_retention(vse, &x.backup.retention_id).or(default_rt),
+            repo_id: &x.backup.repo_id,
+        });
+
+    let nas_inputs = vse.workload_nas.iter().map(|x| SizingInput {
+        site_id: &x.site_id,
+        source_tb: x.source_tb,
+        data_property: find_data_property(vse, &x.data_property_id).or(default_dp),
+        retention: find_retention(vse, &x.backup.retention_id).or(default_rt),
+        repo_id: &x.backup.repo_id,
+    });
+
+    let inputs = vm_inputs.chain(nas_inputs).collect::<Vec<_>>();
+
+    let sites = inputs
+        .iter()
+        .sorted_by_key(|x| x.site_id)
+        .group_by(|x| x.site_id)
+        .into_iter()
+        .map(|(site_id, group)| {
+            let group = group.collect::<Vec<_>>();
+
+            let mut years = (1..=vse.project_length.max(1))
+                .map(|year| YearCapacity {
+                    year,
+                    source_tb: 0.0,
+                    full_tb: 0.0,
+                    incremental_tb: 0.0,
+                    total_tb: 0.0,
+                })
+                .collect::<Vec<_>>();
+
+            for input in &group {
+                let block_clone = vse
+                    .repositories
+                    .iter()
+                    .find(|x| x.repo_id == input.repo_id)
+                    .map(|x| x.storage_type == "xfsRefs")
+                    .unwrap_or(true);
+
+                for year in years.iter_mut() {
+                    let (full_tb, incremental_tb, source_tb) =
+                        workload_capacity(input, year.year, block_clone);
+                    year.source_tb += source_tb;
+                    year.full_tb += full_tb;
+                    year.incremental_tb += incremental_tb;
+                    year.total_tb += full_tb + incremental_tb;
+                }
+            }
+
+            SiteEstimate {
+                site_id: site_id.to_string(),
+                source_tb: group.iter().map(|x| x.source_tb).sum(),
+                years,
+            }
+        })
+        .collect();
+
+    let retentions = inputs
+        .iter()
+        .into_group_map_by(|x| x.retention.map(|r| r.retention_id.as_str()))
+        .into_iter()
+        .sorted_by(|a, b| a.0.cmp(&b.0))
+        .map(|(retention_id, group)| {
+            let (fulls, incrementals) =
+                restore_points(group[0].retention, vse.project_length.max(1));
+
+            RetentionPoints {
+                // workloads without a known retention are counted at the 30 day default
+                retention_id: retention_id.unwrap_or("30D").to_string(),
+                workloads: group.len(),
+                fulls,
+                incrementals,
+            }
+        })
+        .collect();
+
+    Estimate { sites, retentions }
+}
+
+fn find_data_property<'a>(vse: &'a NewVse, id: &str) -> Option<&'a DataProperty> {
+    vse.data_properties
+        .iter()
+        .find(|x| x.data_property_id == id)
+}
+
+fn find_retention<'a>(vse: &'a NewVse, id: &str) -> Option<&'a Retentions> {
+    vse.retentions.iter().find(|x| x.retention_id == id)
+}
+
+/// Full and incremental restore point counts, with yearly points capped at `years_kept`
+fn restore_points(retention: Option<&Retentions>, years_kept: i64) -> (i64, i64) {
+    let (simple, weekly, monthly, yearly) = retention
+        .map(|x| (x.simple, x.weekly, x.monthly, x.yearly))
+        .unwrap_or((30, 0, 0, 0));
+
+    let fulls = 1 + weekly + monthly + yearly.min(years_kept);
+    let incrementals = (simple - 1).max(0);
+
+    (fulls, incrementals)
+}
+
+/// Returns (full TB, incremental TB, source TB) for a workload in the given project year
+fn workload_capacity(input: &SizingInput, year: i64, block_clone: bool) -> (f64, f64, f64) {
+    let (change_rate, compression, growth) = input
+        .data_property
+        .map(|x| (x.change_rate, x.compression, x.growth_factor))
+        .unwrap_or((5, 50, 10));
+
+    let change = change_rate as f64 / 100.0;
+    let reduction = 1.0 - compression as f64 / 100.0;
+    let source_tb = input.source_tb * (1.0 + growth as f64 / 100.0).powi((year - 1) as i32);
+
+    let full_size = source_tb * reduction;
+    let daily_change = full_size * change;
+
+    let (weekly, monthly, yearly) = input
+        .retention
+        .map(|x| (x.weekly, x.monthly, x.yearly.min(year)))
+        .unwrap_or((0, 0, 0));
+
+    let gfs_tb = if block_clone {
+        let gfs_point = |days: f64| f64::min(daily_change * days, full_size);
+        weekly as f64 * gfs_point(WEEK_DAYS)
+            + monthly as f64 * gfs_point(MONTH_DAYS)
+            + yearly as f64 * gfs_point(YEAR_DAYS)
+    } else {
+        (weekly + monthly + yearly) as f64 * full_size
+    };
+
+    let incrementals = restore_points(input.retention, year).1;
+
+    (
+        full_size + gfs_tb,
+        incrementals as f64 * daily_change,
+        source_tb,
+    )
+}
+
+pub fn print_estimate(estimate: &Estimate) {
+    let estimates = &estimate.sites;
+    let mut table = Table::new();
+
+    let year_count = estimates.iter().map(|x| x.years.len()).max().unwrap_or(0);
+
+    let mut header = vec!["Site".to_string(), "Source (TB)".to_string()];
+    header.extend((1..=year_count).map(|x| format!("Year {} (TB)", x)));
+
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(header);
+
+    estimates.iter().for_each(|x| {
+        let mut row = vec![x.site_id.to_string(), format!("{:.2}", x.source_tb)];
+        row.extend(x.years.iter().map(|y| format!("{:.2}", y.total_tb)));
+        table.add_row(row);
+    });
+
+    println!("Estimated performance tier repository capacity");
+    println!("{table}");
+
+    let mut breakdown = Table::new();
+
+    breakdown
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(vec![
+            "Site",
+            "Year",
+            "Source (TB)",
+            "Fulls (TB)",
+            "Incrementals (TB)",
+            "Total (TB)",
+        ]);
+
+    estimates.iter().for_each(|x| {
+        x.years.iter().for_each(|y| {
+            breakdown.add_row(vec![
+                x.site_id.to_string(),
+                y.year.to_string(),
+                format!("{:.2}", y.source_tb),
+                format!("{:.2}", y.full_tb),
+                format!("{:.2}", y.incremental_tb),
+                format!("{:.2}", y.total_tb),
+            ]);
+        })
+    });
+
+    println!("{breakdown}");
+
+    let mut points = Table::new();
+
+    points
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(vec![
+            "Retention",
+            "Workloads",
+            "Fulls per workload",
+            "Incrementals per workload",
+        ]);
+
+    estimate.retentions.iter().for_each(|x| {
+        points.add_row(vec![
+            x.retention_id.to_string(),
+            x.workloads.to_string(),
+            x.fulls.to_string(),
+            x.incrementals.to_string(),
+        ]);
+    });
+
+    println!("Restore points per retention");
+    println!("{points}");
+}
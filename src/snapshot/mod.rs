@@ -0,0 +1,105 @@
+use comfy_table::{
+    modifiers::{UTF8_ROUND_CORNERS, UTF8_SOLID_INNER_BORDERS},
+    presets::UTF8_FULL,
+    Table,
+};
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    helpers::today_serial,
+    models::{
+        rvtools::Vsnapshot,
+        units::{Capacity, Unit},
+    },
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VmSnapshots {
+    pub vm_name: String,
+    pub source: String,
+    pub count: usize,
+    pub oldest_days: f64,
+    pub capacity: Capacity,
+}
+
+/// Groups the snapshots per VM, largest first
+pub fn summarize(snapshots: &[Vsnapshot]) -> Vec<VmSnapshots> {
+    let today = today_serial();
+
+    snapshots
+        .iter()
+        .sorted_by_key(|x| (&x.source, &x.vm_name))
+        .group_by(|x| (&x.source, &x.vm_name))
+        .into_iter()
+        .map(|((source, vm_name), group)| {
+            let group = group.collect::<Vec<_>>();
+            let oldest = group.iter().map(|x| x.date).fold(f64::MAX, f64::min);
+
+            VmSnapshots {
+                vm_name: vm_name.to_string(),
+                source: source.to_string(),
+                count: group.len(),
+                oldest_days: (today - oldest).max(0.0),
+                capacity: group.iter().map(|x| x.capacity).sum(),
+            }
+        })
+        .sorted_by(|a, b| b.capacity.partial_cmp(&a.capacity).unwrap())
+        .collect()
+}
+
+pub fn print_report(summary: &[VmSnapshots]) {
+    let mut table = Table::new();
+
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(vec![
+            "VM Name",
+            "File",
+            "Snapshots",
+            "Oldest (days)",
+            "Size (GiB)",
+        ]);
+
+    summary.iter().for_each(|x| {
+        table.add_row(vec![
+            x.vm_name.to_string(),
+            x.source.to_string(),
+            x.count.to_string(),
+            format!("{:.0}", x.oldest_days),
+            format!("{:.2}", x.capacity.to(Unit::Gib)),
+        ]);
+    });
+
+    println!("{table}");
+}
+
+pub fn print_findings(summary: &[VmSnapshots], max_age_days: i64) {
+    let old = summary
+        .iter()
+        .filter(|x| x.oldest_days > max_age_days as f64)
+        .sorted_by(|a, b| b.oldest_days.partial_cmp(&a.oldest_days).unwrap())
+        .collect::<Vec<_>>();
+
+    if old.is_empty() {
+        println!("No VMs with snapshots older than {} days", max_age_days);
+        return;
+    }
+
+    println!(
+        "Finding: {} VM(s) with snapshots older than {} days, long snapshot chains are a backup risk",
+        old.len(),
+        max_age_days
+    );
+    old.iter().for_each(|x| {
+        println!(
+            "  {} - {} snapshot(s), oldest {:.0} days, {:.2} GiB",
+            x.vm_name,
+            x.count,
+            x.oldest_days,
+            x.capacity.to(Unit::Gib)
+        )
+    });
+}
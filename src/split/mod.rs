@@ -0,0 +1,226 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    models::{
+        cli::{Cli, SplitBy},
+        nas::NasShare,
+        rvtools::{Datacenter, Vinfo},
+        units::Capacity,
+    },
+    nas,
+    pipeline::{Inventory, Settings},
+    vse::IdGenerator,
+};
+
+/// Partition for the physical servers and NAS shares, which have no datacenter or source file
+const OTHER: &str = "other";
+
+/// The workloads that go into one VSE file
+#[derive(Debug, Clone)]
+pub struct Partition {
+    pub name: String,
+    pub datacenters: Vec<Datacenter>,
+    pub nas_shares: Vec<NasShare>,
+}
+
+/// One VSE file written by `write`, with its totals
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IndexEntry {
+    pub name: String,
+    /// File name, in the same directory as the index file
+    pub file: String,
+    pub sites: usize,
+    pub workloads: usize,
+    pub vm_count: usize,
+    /// Physical servers and other non-VM workload units
+    pub physical_count: usize,
+    /// Capacity of the VM and physical workloads
+    pub capacity: f64,
+    pub nas_shares: usize,
+    pub nas_capacity: f64,
+}
+
+/// The index file listing the VSE files of a split
+#[derive(Debug, Clone, Serialize)]
+pub struct Index {
+    pub split_by: String,
+    pub unit: String,
+    pub outputs: Vec<IndexEntry>,
+    pub total: IndexEntry,
+}
+
+/// Splits the workloads of the VMs left after the shape exclusions into partitions
+///
+/// By site the aggregated workloads are split on their site, so the physical servers and NAS
+/// shares go with their site. By datacenter or source file the VMs are split before they are
+/// aggregated, and the physical servers and NAS shares get a partition of their own.
+pub fn partitions(
+    split_by: SplitBy,
+    cli: &Cli,
+    settings: &Settings,
+    inventory: &Inventory,
+) -> Vec<Partition> {
+    let mut partitions = match split_by {
+        SplitBy::Site => {
+            let datacenters = settings.workloads(cli, inventory, &inventory.vms, &settings.shape);
+
+            datacenters
+                .iter()
+                .map(|x| &x.name)
+                .chain(inventory.nas_shares.iter().map(|x| &x.site))
+                .sorted()
+                .dedup()
+                .map(|site| Partition {
+                    name: site.to_string(),
+                    datacenters: datacenters
+                        .iter()
+                        .filter(|x| &x.name == site)
+                        .cloned()
+                        .collect(),
+                    nas_shares: inventory
+                        .nas_shares
+                        .iter()
+                        .filter(|x| &x.site == site)
+                        .cloned()
+                        .collect(),
+                })
+                .collect::<Vec<_>>()
+        }
+        SplitBy::Datacenter | SplitBy::File => {
+            let key = |x: &Vinfo| match split_by {
+                SplitBy::File => x.source.to_string(),
+                _ => x.datacenter.to_string(),
+            };
+
+            let mut partitions = inventory
+                .vms
+                .iter()
+                .cloned()
+                .into_group_map_by(key)
+                .into_iter()
+                .sorted_by(|a, b| a.0.cmp(&b.0))
+                .map(|(name, vms)| Partition {
+                    name,
+                    datacenters: settings.vm_workloads(cli, &vms, &settings.shape),
+                    nas_shares: vec![],
+                })
+                .collect::<Vec<_>>();
+
+            partitions.push(Partition {
+                name: OTHER.to_string(),
+                datacenters: inventory.physical_workloads.clone(),
+                nas_shares: inventory.nas_shares.clone(),
+            });
+
+            partitions
+        }
+    };
+
+    // a datacenter or file only gets the sites its VMs are in
+    partitions
+        .iter_mut()
+        .for_each(|x| x.datacenters.retain(|x| x.vm_count > 0));
+    partitions.retain(|x| !x.datacenters.is_empty() || !x.nas_shares.is_empty());
+    partitions
+}
+
+/// Writes a VSE file per partition, named "{prefix}_{partition}.json", and "{prefix}_index.json"
+pub fn write(
+    prefix: &str,
+    split_by: SplitBy,
+    cli: &Cli,
+    settings: &Settings,
+    partitions: &[Partition],
+) -> Result<Index> {
+    // partition names sanitized and made unique the same way as the VSE ids
+    let mut file_names = IdGenerator::default();
+    let mut outputs = Vec::new();
+
+    for partition in partitions {
+        let vse = settings.vse(cli, &partition.nas_shares, &partition.datacenters)?;
+
+        // files are split on their full path, the file name only uses the file stem
+        let name = match split_by {
+            SplitBy::File => file_stem(&partition.name),
+            _ => partition.name.to_string(),
+        };
+        let file = format!("{}_{}.json", prefix, file_names.id(&partition.name, &name));
+        fs::write(&file, serde_json::to_string_pretty(&vse)?)
+            .with_context(|| format!("Writing {:?}", file))?;
+        println!("VSE file written to: {}", file);
+
+        // next to the index file, so the index only has the file name
+        let file = file_stem(&file) + ".json";
+        outputs.push(entry(partition, file, cli));
+    }
+
+    let total = IndexEntry {
+        name: "total".to_string(),
+        file: String::new(),
+        sites: outputs.iter().map(|x| x.sites).sum(),
+        workloads: outputs.iter().map(|x| x.workloads).sum(),
+        vm_count: outputs.iter().map(|x| x.vm_count).sum(),
+        physical_count: outputs.iter().map(|x| x.physical_count).sum(),
+        capacity: outputs.iter().map(|x| x.capacity).sum(),
+        nas_shares: outputs.iter().map(|x| x.nas_shares).sum(),
+        nas_capacity: outputs.iter().map(|x| x.nas_capacity).sum(),
+    };
+
+    let index = Index {
+        split_by: format!("{:?}", split_by).to_lowercase(),
+        unit: cli.unit.to_string(),
+        outputs,
+        total,
+    };
+
+    let index_file = format!("{}_index.json", prefix);
+    fs::write(&index_file, serde_json::to_string_pretty(&index)?)
+        .with_context(|| format!("Writing {:?}", index_file))?;
+    println!("Index written to: {}", index_file);
+
+    Ok(index)
+}
+
+fn entry(partition: &Partition, file: String, cli: &Cli) -> IndexEntry {
+    let count = |vm: bool| {
+        partition
+            .datacenters
+            .iter()
+            .filter(|x| (x.workload_type == "VM") == vm)
+            .map(|x| x.vm_count)
+            .sum()
+    };
+    let capacity: Capacity = partition.datacenters.iter().map(|x| x.capacity).sum();
+    // sites with workloads, not the copy only sites of the VSE file
+    let sites = partition
+        .datacenters
+        .iter()
+        .map(|x| &x.name)
+        .chain(partition.nas_shares.iter().map(|x| &x.site))
+        .unique()
+        .count();
+
+    IndexEntry {
+        name: partition.name.to_string(),
+        file,
+        sites,
+        workloads: partition.datacenters.len() + partition.nas_shares.len(),
+        vm_count: count(true),
+        physical_count: count(false),
+        capacity: capacity.to(cli.unit),
+        nas_shares: partition.nas_shares.len(),
+        nas_capacity: nas::total_capacity(&partition.nas_shares).to(cli.unit),
+    }
+}
+
+/// "exports/rv_lon.xlsx" -> "rv_lon"
+fn file_stem(source: &str) -> String {
+    Path::new(source)
+        .file_stem()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_else(|| source.to_string())
+}
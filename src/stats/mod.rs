@@ -0,0 +1,237 @@
+use anyhow::{anyhow, Result};
+use comfy_table::{
+    modifiers::{UTF8_ROUND_CORNERS, UTF8_SOLID_INNER_BORDERS},
+    presets::UTF8_FULL,
+    Table,
+};
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::models::{
+    cli::Cli,
+    rvtools::Vinfo,
+    units::{Capacity, Unit},
+};
+
+/// Size distribution of a group of VMs, sizes are in `VmStats::unit`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SizeStats {
+    pub datacenter: String,
+    pub cluster: String,
+    pub vm_count: usize,
+    pub total: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+    /// VMs larger than each of `VmStats::thresholds_gib`
+    pub above: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LargeVm {
+    pub vm_name: String,
+    pub datacenter: String,
+    pub cluster: String,
+    pub capacity: f64,
+}
+
+/// VM size statistics for all the VMs and per datacenter and cluster
+#[derive(Debug, Clone, Serialize)]
+pub struct VmStats {
+    pub unit: String,
+    pub thresholds_gib: Vec<f64>,
+    pub global: SizeStats,
+    pub clusters: Vec<SizeStats>,
+    /// The `--top-vms` largest VMs, largest first
+    pub largest: Vec<LargeVm>,
+}
+
+impl VmStats {
+    pub fn from_vms(vms: &[Vinfo], cli: &Cli) -> Result<Self> {
+        let unit = cli.unit.vm_unit();
+
+        let mut thresholds_gib = cli.size_thresholds.clone();
+
+        if thresholds_gib.iter().any(|x| !x.is_finite() || *x <= 0.0) {
+            return Err(anyhow!("Size thresholds must be sizes in GiB above 0"));
+        }
+
+        thresholds_gib.sort_by(f64::total_cmp);
+        thresholds_gib.dedup();
+
+        let stats = |datacenter: &str, cluster: &str, vms: &[&Vinfo]| {
+            size_stats(datacenter, cluster, vms, &thresholds_gib, unit)
+        };
+
+        let clusters = vms
+            .iter()
+            .into_group_map_by(|x| (&x.datacenter, &x.cluster))
+            .into_iter()
+            .sorted_by(|a, b| a.0.cmp(&b.0))
+            .map(|((datacenter, cluster), vms)| stats(datacenter, cluster, &vms))
+            .collect();
+
+        let largest = vms
+            .iter()
+            .sorted_by(|a, b| b.capacity.partial_cmp(&a.capacity).unwrap())
+            .take(cli.top_vms)
+            .map(|x| LargeVm {
+                vm_name: x.vm_name.to_string(),
+                datacenter: x.datacenter.to_string(),
+                cluster: x.cluster.to_string(),
+                capacity: x.capacity.to(unit),
+            })
+            .collect();
+
+        Ok(Self {
+            unit: unit.to_string(),
+            global: stats("All", "All", &vms.iter().collect::<Vec<_>>()),
+            thresholds_gib,
+            clusters,
+            largest,
+        })
+    }
+}
+
+fn size_stats(
+    datacenter: &str,
+    cluster: &str,
+    vms: &[&Vinfo],
+    thresholds_gib: &[f64],
+    unit: Unit,
+) -> SizeStats {
+    let sizes = vms
+        .iter()
+        .map(|x| x.capacity.to(unit))
+        .sorted_by(|a, b| a.partial_cmp(b).unwrap())
+        .collect::<Vec<_>>();
+    let total: f64 = sizes.iter().sum();
+
+    SizeStats {
+        datacenter: datacenter.to_string(),
+        cluster: cluster.to_string(),
+        vm_count: sizes.len(),
+        total,
+        mean: if sizes.is_empty() {
+            0.0
+        } else {
+            total / sizes.len() as f64
+        },
+        median: percentile(&sizes, 50.0),
+        p90: percentile(&sizes, 90.0),
+        p99: percentile(&sizes, 99.0),
+        max: sizes.last().copied().unwrap_or_default(),
+        above: thresholds_gib
+            .iter()
+            .map(|x| {
+                let threshold = Capacity::new(*x, Unit::Gib).to(unit);
+                sizes.iter().filter(|size| **size > threshold).count()
+            })
+            .collect(),
+    }
+}
+
+/// Percentile of sorted values, interpolating between the two closest ranks
+fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+
+    let rank = percent / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// The global statistics, one line each like the rest of the final summary
+pub fn print_summary(stats: &VmStats) {
+    let global = &stats.global;
+    let unit = &stats.unit;
+
+    println!("Median VM Size: {:.2} {}", global.median, unit);
+    println!("P90 VM Size: {:.2} {}", global.p90, unit);
+    println!("P99 VM Size: {:.2} {}", global.p99, unit);
+
+    if let Some(largest) = stats.largest.first() {
+        println!(
+            "Largest VM: {} ({:.2} {})",
+            largest.vm_name, largest.capacity, unit
+        );
+    }
+
+    stats
+        .thresholds_gib
+        .iter()
+        .zip(&global.above)
+        .for_each(|(threshold, count)| println!("VMs over {} GiB: {}", threshold, count));
+}
+
+/// Per-cluster statistics and the largest VMs as tables
+pub fn print_tables(stats: &VmStats) {
+    let unit = &stats.unit;
+    let mut table = Table::new();
+
+    let mut header = vec![
+        "Datacenter".to_string(),
+        "Cluster".to_string(),
+        "VM Count".to_string(),
+        format!("Mean ({})", unit),
+        format!("Median ({})", unit),
+        format!("P90 ({})", unit),
+        format!("P99 ({})", unit),
+        format!("Max ({})", unit),
+    ];
+    header.extend(stats.thresholds_gib.iter().map(|x| format!("> {} GiB", x)));
+
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(header);
+
+    stats
+        .clusters
+        .iter()
+        .chain(std::iter::once(&stats.global))
+        .for_each(|x| {
+            let mut row = vec![
+                x.datacenter.to_string(),
+                x.cluster.to_string(),
+                x.vm_count.to_string(),
+                format!("{:.2}", x.mean),
+                format!("{:.2}", x.median),
+                format!("{:.2}", x.p90),
+                format!("{:.2}", x.p99),
+                format!("{:.2}", x.max),
+            ];
+            row.extend(x.above.iter().map(|count| count.to_string()));
+            table.add_row(row);
+        });
+    println!("{table}");
+
+    let mut table = Table::new();
+
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(vec![
+            "VM Name".to_string(),
+            "Datacenter".to_string(),
+            "Cluster".to_string(),
+            format!("Capacity ({})", unit),
+        ]);
+
+    stats.largest.iter().for_each(|x| {
+        table.add_row(vec![
+            x.vm_name.to_string(),
+            x.datacenter.to_string(),
+            x.cluster.to_string(),
+            format!("{:.2}", x.capacity),
+        ]);
+    });
+    println!("{table}");
+}
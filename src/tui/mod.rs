@@ -0,0 +1,575 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::Result;
+use itertools::Itertools;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+
+use crate::{
+    models::{
+        cli::Cli,
+        new_model::{Mapper, NewVse},
+        rvtools::Vinfo,
+        shape::{ClusterAssignment, ClusterRef, ShapeConfig},
+        units::{Capacity, Unit},
+    },
+    pipeline::site_for_vm,
+    vse::validate_retention,
+};
+
+const HELP: &str =
+    "↑/↓ move  Enter open  Esc back  Space exclude  s site  r retention  w write  q quit";
+
+/// Drill down position, DC -> cluster -> VM
+enum Level {
+    Datacenters,
+    Clusters(String),
+    Vms(String, String),
+}
+
+#[derive(Clone, Copy)]
+enum Field {
+    Site,
+    Retention,
+}
+
+struct Input {
+    field: Field,
+    value: String,
+}
+
+/// A row of the table at the current level
+struct Entry {
+    name: String,
+    cells: Vec<String>,
+    excluded: bool,
+}
+
+struct App {
+    vms: Vec<Vinfo>,
+    shape: ShapeConfig,
+    /// The run's options and DC map, so the site totals match the VSE that is written
+    cli: Cli,
+    dc_map: Option<Vec<Mapper>>,
+    unit: Unit,
+    level: Level,
+    state: TableState,
+    input: Option<Input>,
+    message: String,
+}
+
+/// Runs the TUI over the loaded VMs, `build` turns the VMs and shape config into the VSE
+pub fn run(
+    cli: &Cli,
+    vms: Vec<Vinfo>,
+    shape: ShapeConfig,
+    dc_map: Option<Vec<Mapper>>,
+    build: impl Fn(&[Vinfo], &ShapeConfig) -> Result<NewVse>,
+) -> Result<()> {
+    let vse_path = match &cli.output_file {
+        Some(file_name) if file_name.contains(".json") => file_name.to_string(),
+        Some(file_name) => format!("{}.json", file_name),
+        None => "vse_tui.json".to_string(),
+    };
+    let config_path = cli
+        .shape_config
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("shape_config.json"));
+
+    let mut app = App {
+        vms,
+        shape,
+        cli: cli.clone(),
+        dc_map,
+        unit: cli.unit,
+        level: Level::Datacenters,
+        state: TableState::default().with_selected(0),
+        input: None,
+        message: String::new(),
+    };
+
+    let write = |app: &App| -> Result<()> {
+        let vse = build(&app.vms, &app.shape)?;
+        fs::write(&vse_path, serde_json::to_string_pretty(&vse)?)?;
+        fs::write(&config_path, serde_json::to_string_pretty(&app.shape)?)?;
+        Ok(())
+    };
+
+    let mut terminal = ratatui::init();
+    let result = app.event_loop(&mut terminal, |app| match write(app) {
+        Ok(()) => format!(
+            "VSE file written to: {}, shape config written to: {}",
+            vse_path,
+            config_path.display()
+        ),
+        Err(e) => format!("Write failed: {}", e),
+    });
+    ratatui::restore();
+
+    result
+}
+
+impl App {
+    fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        write: impl Fn(&App) -> String,
+    ) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            if self.input.is_some() {
+                self.edit(key.code);
+                continue;
+            }
+
+            self.message.clear();
+
+            match key.code {
+                KeyCode::Char('q') => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::Up | KeyCode::Char('k') => self.state.select_previous(),
+                KeyCode::Down | KeyCode::Char('j') => self.select_next(),
+                KeyCode::Enter | KeyCode::Right => self.drill_in(),
+                KeyCode::Esc | KeyCode::Backspace | KeyCode::Left => self.back(),
+                KeyCode::Char(' ') => self.toggle(),
+                KeyCode::Char('s') => self.start_input(Field::Site),
+                KeyCode::Char('r') => self.start_input(Field::Retention),
+                KeyCode::Char('w') => self.message = write(self),
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [title, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list, totals] =
+            Layout::horizontal([Constraint::Percentage(70), Constraint::Percentage(30)])
+                .areas(body);
+
+        let path = match &self.level {
+            Level::Datacenters => "Datacenters".to_string(),
+            Level::Clusters(dc) => format!("Datacenters > {}", dc),
+            Level::Vms(dc, cluster) => format!("Datacenters > {} > {}", dc, cluster),
+        };
+        frame.render_widget(
+            Paragraph::new(path).style(Style::default().add_modifier(Modifier::BOLD)),
+            title,
+        );
+
+        let (header, widths) = self.columns();
+        let rows = self.entries().into_iter().map(|x| {
+            let style = if x.excluded {
+                Style::default().add_modifier(Modifier::DIM | Modifier::CROSSED_OUT)
+            } else {
+                Style::default()
+            };
+            Row::new(x.cells).style(style)
+        });
+        let table = Table::new(rows, widths)
+            .header(Row::new(header).style(Style::default().add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::ALL))
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, list, &mut self.state);
+
+        frame.render_widget(
+            Paragraph::new(self.totals())
+                .block(Block::default().borders(Borders::ALL).title("Totals")),
+            totals,
+        );
+
+        let status = match &self.input {
+            Some(input) => {
+                let field = match input.field {
+                    Field::Site => "Site",
+                    Field::Retention => "Retention (30D1W1M1Y)",
+                };
+                format!(
+                    "{}: {}_  (Enter to set, empty to clear, Esc to cancel)",
+                    field, input.value
+                )
+            }
+            None if !self.message.is_empty() => self.message.clone(),
+            None => HELP.to_string(),
+        };
+        frame.render_widget(Paragraph::new(status), footer);
+    }
+
+    fn columns(&self) -> (Vec<String>, Vec<Constraint>) {
+        let capacity = format!("Capacity ({})", self.unit);
+
+        match self.level {
+            Level::Datacenters => (
+                vec![
+                    "Datacenter".to_string(),
+                    "Clusters".to_string(),
+                    "VMs".to_string(),
+                    capacity,
+                ],
+                vec![
+                    Constraint::Fill(1),
+                    Constraint::Length(10),
+                    Constraint::Length(14),
+                    Constraint::Length(16),
+                ],
+            ),
+            Level::Clusters(_) => (
+                vec![
+                    "Cluster".to_string(),
+                    "Site".to_string(),
+                    "Retention".to_string(),
+                    "VMs".to_string(),
+                    capacity,
+                ],
+                vec![
+                    Constraint::Fill(1),
+                    Constraint::Fill(1),
+                    Constraint::Length(12),
+                    Constraint::Length(14),
+                    Constraint::Length(16),
+                ],
+            ),
+            Level::Vms(..) => (
+                vec![
+                    "VM Name".to_string(),
+                    format!("Capacity ({})", self.unit.vm_unit()),
+                    "Power State".to_string(),
+                ],
+                vec![
+                    Constraint::Fill(1),
+                    Constraint::Length(16),
+                    Constraint::Length(14),
+                ],
+            ),
+        }
+    }
+
+    fn entries(&self) -> Vec<Entry> {
+        match &self.level {
+            Level::Datacenters => self
+                .vms
+                .iter()
+                .sorted_by_key(|x| &x.datacenter)
+                .group_by(|x| &x.datacenter)
+                .into_iter()
+                .map(|(dc, group)| {
+                    let group = group.collect::<Vec<_>>();
+                    let clusters = group.iter().map(|x| &x.cluster).unique().count();
+                    Entry {
+                        name: dc.to_string(),
+                        cells: vec![
+                            dc.to_string(),
+                            clusters.to_string(),
+                            self.vm_counts(&group),
+                            self.capacity(&group),
+                        ],
+                        excluded: self.shape.excluded_datacenters.contains(dc),
+                    }
+                })
+                .collect(),
+            Level::Clusters(dc) => self
+                .vms
+                .iter()
+                .filter(|x| &x.datacenter == dc)
+                .sorted_by_key(|x| &x.cluster)
+                .group_by(|x| &x.cluster)
+                .into_iter()
+                .map(|(cluster, group)| {
+                    let group = group.collect::<Vec<_>>();
+                    let assignment = self.shape.assignment(dc, cluster);
+                    Entry {
+                        name: cluster.to_string(),
+                        cells: vec![
+                            cluster.to_string(),
+                            assignment
+                                .and_then(|x| x.site.clone())
+                                .unwrap_or_else(|| dc.to_string()),
+                            assignment
+                                .and_then(|x| x.retention.clone())
+                                .unwrap_or_else(|| "default".to_string()),
+                            self.vm_counts(&group),
+                            self.capacity(&group),
+                        ],
+                        excluded: self.shape.excluded_datacenters.contains(dc)
+                            || self.shape.is_cluster_excluded(dc, cluster),
+                    }
+                })
+                .collect(),
+            Level::Vms(dc, cluster) => self
+                .vms
+                .iter()
+                .filter(|x| &x.datacenter == dc && &x.cluster == cluster)
+                .sorted_by(|a, b| b.capacity.partial_cmp(&a.capacity).unwrap())
+                .map(|x| Entry {
+                    name: x.vm_name.to_string(),
+                    cells: vec![
+                        x.vm_name.to_string(),
+                        format!("{:.2}", x.capacity.to(self.unit.vm_unit())),
+                        x.powerstate.to_string(),
+                    ],
+                    excluded: self.shape.is_excluded(x),
+                })
+                .collect(),
+        }
+    }
+
+    /// Included of all VMs
+    fn vm_counts(&self, vms: &[&Vinfo]) -> String {
+        let included = vms.iter().filter(|x| !self.shape.is_excluded(x)).count();
+        format!("{}/{}", included, vms.len())
+    }
+
+    /// Capacity of the included VMs
+    fn capacity(&self, vms: &[&Vinfo]) -> String {
+        let capacity: Capacity = vms
+            .iter()
+            .filter(|x| !self.shape.is_excluded(x))
+            .map(|x| x.capacity)
+            .sum();
+        format!("{:.2}", capacity.to(self.unit))
+    }
+
+    fn totals(&self) -> Vec<Line<'static>> {
+        let included = self
+            .vms
+            .iter()
+            .filter(|x| !self.shape.is_excluded(x))
+            .collect::<Vec<_>>();
+        let capacity: Capacity = included.iter().map(|x| x.capacity).sum();
+
+        let mut lines = vec![
+            Line::from(format!("VMs: {}", included.len())),
+            Line::from(format!("Excluded VMs: {}", self.vms.len() - included.len())),
+            Line::from(format!(
+                "Capacity: {:.2} {}",
+                capacity.to(self.unit),
+                self.unit
+            )),
+            Line::from(""),
+            Line::from("Per site:"),
+        ];
+
+        included
+            .iter()
+            .map(|x| (site_for_vm(x, &self.cli, &self.dc_map, &self.shape), x))
+            .sorted_by(|a, b| a.0.cmp(&b.0))
+            .group_by(|(site, _)| site.clone())
+            .into_iter()
+            .for_each(|(site, group)| {
+                let mut capacity = Capacity::default();
+                let mut vm_count = 0;
+                group.for_each(|(_, x)| {
+                    capacity += x.capacity;
+                    vm_count += 1;
+                });
+                lines.push(Line::from(format!(
+                    "{}: {} VMs, {:.2} {}",
+                    site.as_deref().unwrap_or("Outside the DC map"),
+                    vm_count,
+                    capacity.to(self.unit),
+                    self.unit
+                )));
+            });
+
+        lines
+    }
+
+    fn selected(&self) -> Option<Entry> {
+        self.state
+            .selected()
+            .and_then(|i| self.entries().into_iter().nth(i))
+    }
+
+    fn select_next(&mut self) {
+        let last = self.entries().len().saturating_sub(1);
+        let next = self.state.selected().map_or(0, |i| (i + 1).min(last));
+        self.state.select(Some(next));
+    }
+
+    /// Moves to `level` with the row named `name` selected
+    fn set_level(&mut self, level: Level, name: Option<&str>) {
+        self.level = level;
+        let position = name
+            .and_then(|name| self.entries().iter().position(|x| x.name == name))
+            .unwrap_or(0);
+        self.state.select(Some(position));
+    }
+
+    fn drill_in(&mut self) {
+        let Some(entry) = self.selected() else {
+            return;
+        };
+
+        match &self.level {
+            Level::Datacenters => self.set_level(Level::Clusters(entry.name), None),
+            Level::Clusters(dc) => self.set_level(Level::Vms(dc.to_string(), entry.name), None),
+            Level::Vms(..) => {}
+        }
+    }
+
+    fn back(&mut self) {
+        match &self.level {
+            Level::Datacenters => {}
+            Level::Clusters(dc) => {
+                let dc = dc.to_string();
+                self.set_level(Level::Datacenters, Some(&dc))
+            }
+            Level::Vms(dc, cluster) => {
+                let cluster = cluster.to_string();
+                self.set_level(Level::Clusters(dc.to_string()), Some(&cluster))
+            }
+        }
+    }
+
+    fn toggle(&mut self) {
+        let Some(entry) = self.selected() else {
+            return;
+        };
+
+        match &self.level {
+            Level::Datacenters => toggle(&mut self.shape.excluded_datacenters, entry.name),
+            Level::Clusters(dc) => toggle(
+                &mut self.shape.excluded_clusters,
+                ClusterRef {
+                    datacenter: dc.to_string(),
+                    cluster: entry.name,
+                },
+            ),
+            Level::Vms(..) => toggle(&mut self.shape.excluded_vms, entry.name),
+        }
+    }
+
+    /// Clusters the site or retention input applies to, all the DC's clusters at DC level
+    fn targets(&self) -> Vec<(String, String)> {
+        let Some(entry) = self.selected() else {
+            return vec![];
+        };
+
+        match &self.level {
+            Level::Datacenters => self
+                .vms
+                .iter()
+                .filter(|x| x.datacenter == entry.name)
+                .map(|x| (x.datacenter.to_string(), x.cluster.to_string()))
+                .unique()
+                .collect(),
+            Level::Clusters(dc) => vec![(dc.to_string(), entry.name)],
+            Level::Vms(..) => vec![],
+        }
+    }
+
+    fn start_input(&mut self, field: Field) {
+        let targets = self.targets();
+        if targets.is_empty() {
+            self.message = "Sites and retentions are assigned to clusters".to_string();
+            return;
+        }
+
+        let current = match &self.level {
+            Level::Clusters(_) => self
+                .shape
+                .assignment(&targets[0].0, &targets[0].1)
+                .and_then(|x| match field {
+                    Field::Site => x.site.clone(),
+                    Field::Retention => x.retention.clone(),
+                }),
+            _ => None,
+        };
+
+        self.input = Some(Input {
+            field,
+            value: current.unwrap_or_default(),
+        });
+    }
+
+    fn edit(&mut self, key: KeyCode) {
+        let Some(input) = self.input.as_mut() else {
+            return;
+        };
+
+        match key {
+            KeyCode::Char(c) => input.value.push(c),
+            KeyCode::Backspace => {
+                input.value.pop();
+            }
+            KeyCode::Esc => self.input = None,
+            KeyCode::Enter => {
+                if let Some(input) = self.input.take() {
+                    self.assign(input);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn assign(&mut self, input: Input) {
+        let value = Some(input.value.trim().to_string()).filter(|x| !x.is_empty());
+
+        if let (Field::Retention, Some(pattern)) = (input.field, &value) {
+            if let Err(e) = validate_retention(pattern) {
+                self.message = e.to_string();
+                return;
+            }
+        }
+
+        for (datacenter, cluster) in self.targets() {
+            let index = match self
+                .shape
+                .assignments
+                .iter()
+                .position(|x| x.datacenter == datacenter && x.cluster == cluster)
+            {
+                Some(index) => index,
+                None => {
+                    self.shape.assignments.push(ClusterAssignment {
+                        datacenter,
+                        cluster,
+                        site: None,
+                        retention: None,
+                    });
+                    self.shape.assignments.len() - 1
+                }
+            };
+
+            let assignment = &mut self.shape.assignments[index];
+            match input.field {
+                Field::Site => assignment.site = value.clone(),
+                Field::Retention => assignment.retention = value.clone(),
+            }
+        }
+
+        self.shape
+            .assignments
+            .retain(|x| x.site.is_some() || x.retention.is_some());
+    }
+}
+
+/// Adds `item` to the list, or removes it when it is already there
+fn toggle<T: PartialEq>(list: &mut Vec<T>, item: T) {
+    match list.iter().position(|x| *x == item) {
+        Some(index) => {
+            list.remove(index);
+        }
+        None => list.push(item),
+    }
+}
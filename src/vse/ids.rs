@@ -0,0 +1,131 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+
+use crate::helpers::load_json;
+
+/// Ids by object key, e.g. "workload:LON-DC1|Prod|VM|" -> "LON-DC1_Prod_workload"
+pub type IdMap = BTreeMap<String, String>;
+
+/// Hands out VSE ids that are unique within a `NewVse` and only use [A-Za-z0-9_-]
+///
+/// The same key always gets the same id: from the id map when it has one, otherwise from the
+/// sanitized name with a hash of the key appended when another object already has that id.
+#[derive(Debug, Clone, Default)]
+pub struct IdGenerator {
+    ids: IdMap,
+    used: HashSet<String>,
+}
+
+impl IdGenerator {
+    /// Starts from the ids of a previous run, which are kept for the same keys
+    pub fn new(ids: IdMap) -> Self {
+        let used = ids.values().cloned().collect();
+        Self { ids, used }
+    }
+
+    /// Id of the object with `key`, built from `name` the first time the key is seen
+    pub fn id(&mut self, key: &str, name: &str) -> String {
+        if let Some(id) = self.ids.get(key) {
+            return id.to_string();
+        }
+
+        let base = sanitize(name);
+        let mut id = base.clone();
+
+        if self.used.contains(&id) {
+            id = format!("{}_{:06x}", base, fnv1a(key) & 0xffffff);
+        }
+
+        let mut n = 2;
+        while self.used.contains(&id) {
+            id = format!("{}_{:06x}_{}", base, fnv1a(key) & 0xffffff, n);
+            n += 1;
+        }
+
+        self.used.insert(id.clone());
+        self.ids.insert(key.to_string(), id.clone());
+        id
+    }
+
+    /// All the ids handed out, including the ones loaded from a previous run
+    pub fn into_map(self) -> IdMap {
+        self.ids
+    }
+}
+
+/// Loads an id map written by `save_map`, or an empty map when the file doesn't exist yet
+pub fn load_map(path: &Path) -> Result<IdMap> {
+    if !path.exists() {
+        return Ok(IdMap::new());
+    }
+
+    load_json(path)
+}
+
+pub fn save_map(path: &Path, ids: &IdMap) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(ids)?)
+        .with_context(|| format!("Writing {:?}", path))
+}
+
+/// Replaces runs of characters outside [A-Za-z0-9-] with a single '_', accented latin
+/// letters are replaced by their base letter first, e.g. "Zürich" -> "Zurich"
+pub fn sanitize(name: &str) -> String {
+    let mut id = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '-' {
+            id.push(c);
+        } else if let Some(ascii) = fold_latin(c) {
+            id.push_str(ascii);
+        } else if !id.ends_with('_') {
+            id.push('_');
+        }
+    }
+
+    let id = id.trim_matches('_');
+    if id.is_empty() {
+        "id".to_string()
+    } else {
+        id.to_string()
+    }
+}
+
+fn fold_latin(c: char) -> Option<&'static str> {
+    let ascii = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => "a",
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' => "A",
+        'ç' => "c",
+        'Ç' => "C",
+        'è' | 'é' | 'ê' | 'ë' => "e",
+        'È' | 'É' | 'Ê' | 'Ë' => "E",
+        'ì' | 'í' | 'î' | 'ï' => "i",
+        'Ì' | 'Í' | 'Î' | 'Ï' => "I",
+        'ñ' => "n",
+        'Ñ' => "N",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => "o",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' => "O",
+        'ù' | 'ú' | 'û' | 'ü' => "u",
+        'Ù' | 'Ú' | 'Û' | 'Ü' => "U",
+        'ý' | 'ÿ' => "y",
+        'Ý' => "Y",
+        'ß' => "ss",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'œ' => "oe",
+        'Œ' => "OE",
+        _ => return None,
+    };
+    Some(ascii)
+}
+
+/// 64-bit FNV-1a, unlike `DefaultHasher` it is the same across Rust releases
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
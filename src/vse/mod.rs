@@ -1,43 +1,81 @@
-use anyhow::Result;
+mod ids;
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use itertools::Itertools;
 use regex::Regex;
 
-use crate::models::{
-    new_model::{
-        Backup, CapArchTier, DataProperty, NewVse, PerfTierRepo, Retentions, Site, Window, Workload,
+use crate::{
+    growth::GrowthPolicy,
+    models::{
+        nas::NasShare,
+        new_model::{
+            Backup, CapArchTier, Copy, NewVse, PerfTierRepo, Retentions, Site, Workload,
+            WorkloadNas,
+        },
+        rvtools::Datacenter,
+        units::{Capacity, Unit},
     },
-    rvtools::Datacenter,
+    window::WindowPolicy,
 };
 
+pub use ids::{load_map, sanitize, save_map, IdGenerator, IdMap};
+
+/// VSE sizes the workload source data (sourceTB) in decimal terabytes
+pub const VSE_SOURCE_UNIT: Unit = Unit::Tb;
+
+#[allow(clippy::too_many_arguments)]
 pub fn vse_construct(
     datacenter_strings: Vec<String>,
-    datacenters: &Vec<Datacenter>,
+    datacenters: &[Datacenter],
     retention: Option<String>,
+    windows: &WindowPolicy,
+    nas_shares: &[NasShare],
+    nas_retention: Option<&str>,
+    growth: &GrowthPolicy,
+    ids: &mut IdGenerator,
 ) -> Result<NewVse> {
-    let sites = datacenter_strings
+    // sites that only receive backup copies still need a site and a repo
+    let copy_sites = datacenters
+        .iter()
+        .filter_map(|x| x.copy_site.as_ref())
+        .filter(|x| !datacenter_strings.contains(x))
+        .sorted()
+        .dedup()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let site_ids = datacenter_strings
         .iter()
-        .map(|x| Site::new(x.to_string(), x.to_string()))
+        .chain(&copy_sites)
+        .chain(nas_shares.iter().map(|x| &x.site))
+        .chain(nas_shares.iter().filter_map(|x| x.copy_site.as_ref()))
+        .map(|x| (x.to_string(), ids.id(&format!("site:{}", x), x)))
+        .collect::<HashMap<String, String>>();
+
+    let mut sites = datacenter_strings
+        .iter()
+        .chain(&copy_sites)
+        .map(|x| Site::new(site_ids[x].to_string(), x.to_string()))
         .collect::<Vec<Site>>();
 
     // performance tier repos
-    let repos = datacenter_strings
+    let repo_ids = datacenter_strings
         .iter()
+        .chain(&copy_sites)
         .map(|x| {
-            PerfTierRepo::new(
-                format!("{}_repo", x),
-                format!("{}_repo", x),
-                x.to_string(),
-                false,
-                false,
-                false,
-                0,
-                0,
-                "general-s3compatible-capacity".to_string(),
-                "general-glacier-archive".to_string(),
-                "xfsRefs".to_string(),
-                false,
-                false,
-                false,
-            )
+            let name = format!("{}_repo", x);
+            (x.to_string(), (ids.id(&format!("repo:{}", x), &name), name))
+        })
+        .collect::<HashMap<String, (String, String)>>();
+
+    let mut repos = datacenter_strings
+        .iter()
+        .chain(&copy_sites)
+        .map(|x| {
+            let (id, name) = &repo_ids[x];
+            perf_repo(id, name, &site_ids[x])
         })
         .collect::<Vec<PerfTierRepo>>();
 
@@ -55,88 +93,238 @@ pub fn vse_construct(
         true,
     );
 
-    let data_property = DataProperty::new(
-        "dpopt".to_string(),
-        "Generic Optimistic".to_string(),
-        5,
-        50,
-        10,
-        true,
-    );
-
-    let window = Window::new(
-        "bw12".to_string(),
-        "backup_window1".to_string(),
-        24,
-        12,
-        true,
-    );
+    let all_sites = datacenter_strings
+        .iter()
+        .cloned()
+        .chain(nas_shares.iter().map(|x| x.site.to_string()))
+        .collect::<Vec<_>>();
 
-    let mut simple = 30;
-    let mut weekly = 0;
-    let mut monthly = 0;
-    let mut yearly = 0;
+    let data_properties = growth.data_properties(&all_sites);
 
-    if let Some(retention) = retention {
-        let re = Regex::new(r"(\d+)D(\d+)W(\d+)M(\d+)Y").unwrap();
+    let retention = parse_retention("rt1", retention.as_deref(), true)?;
 
-        let caps = re.captures(&retention).unwrap();
+    let mut retentions = vec![retention];
 
-        simple = caps[1].parse::<i64>().unwrap();
-        weekly = caps[2].parse::<i64>().unwrap();
-        monthly = caps[3].parse::<i64>().unwrap();
-        yearly = caps[4].parse::<i64>().unwrap();
+    if !nas_shares.is_empty() {
+        retentions.push(parse_retention("rt_nas", nas_retention, false)?);
     }
 
-    let retention = Retentions::new(
-        "rt1".to_string(),
-        "30D".to_string(),
-        "Instance".to_string(),
-        simple,
-        weekly,
-        monthly,
-        yearly,
-        true,
-    );
+    for pattern in datacenters
+        .iter()
+        .filter_map(|x| x.retention.as_deref())
+        .sorted()
+        .dedup()
+    {
+        retentions.push(parse_retention(
+            &retention_id(ids, pattern),
+            Some(pattern),
+            false,
+        )?);
+    }
 
     let workloads = datacenters
         .iter()
         .map(|x| {
+            // the cluster name alone repeats across sites, retentions, workload types and
+            // copy sites
+            let mut key = format!(
+                "workload:{}|{}|{}|{}",
+                x.name,
+                x.cluster,
+                x.workload_type,
+                x.retention.as_deref().unwrap_or_default()
+            );
+            if let Some(copy_site) = &x.copy_site {
+                key = format!("{}|{}", key, copy_site);
+            }
+            let id = ids.id(&key, &format!("{}_{}_workload", x.name, x.cluster));
+
+            let retention_id = x
+                .retention
+                .as_deref()
+                .map(|x| retention_id(ids, x))
+                .unwrap_or_else(|| "rt1".to_string());
+
             let backup = Backup::new(
-                "rt1".to_string(),
-                format!("{}_repo", x.name),
-                "bw12".to_string(),
+                retention_id.to_string(),
+                repo_ids[&x.name].0.to_string(),
+                windows.window_for(&x.name, &x.workload_type).id(),
             );
 
-            let copies = Backup::new("".to_string(), "".to_string(), "".to_string());
+            let copies = match &x.copy_site {
+                Some(copy_site) => Backup::new(
+                    retention_id,
+                    repo_ids[copy_site].0.to_string(),
+                    windows.window_for(copy_site, &x.workload_type).id(),
+                ),
+                None => Backup::new("".to_string(), "".to_string(), "".to_string()),
+            };
 
             Workload::new(
-                format!("{}_workload", x.cluster),
+                id,
                 true,
                 format!("{}_workload", x.cluster),
-                x.name.to_string(),
-                false,
-                x.capacity,
+                site_ids[&x.name].to_string(),
+                x.large_block,
+                x.capacity.to(VSE_SOURCE_UNIT),
                 x.vm_count as i64,
-                "VM".to_string(),
-                "dpopt".to_string(),
+                x.workload_type.to_string(),
+                growth.data_property_id(&x.name),
                 backup,
-                false,
+                x.copy_site.is_some(),
                 copies,
             )
         })
         .collect::<Vec<Workload>>();
 
+    let nas_sites = nas_shares
+        .iter()
+        .flat_map(|x| std::iter::once(&x.site).chain(x.copy_site.as_ref()))
+        .sorted()
+        .dedup()
+        .collect::<Vec<_>>();
+
+    sites.extend(
+        nas_sites
+            .iter()
+            .filter(|x| !datacenter_strings.contains(x) && !copy_sites.contains(x))
+            .map(|x| Site::new(site_ids[*x].to_string(), x.to_string())),
+    );
+
+    let nas_repo_ids = nas_sites
+        .iter()
+        .map(|x| {
+            let name = format!("{}_nas_repo", x);
+            (
+                x.to_string(),
+                (ids.id(&format!("nas_repo:{}", x), &name), name),
+            )
+        })
+        .collect::<HashMap<String, (String, String)>>();
+
+    repos.extend(nas_sites.iter().map(|x| {
+        let (id, name) = &nas_repo_ids[*x];
+        perf_repo(id, name, &site_ids[*x])
+    }));
+
+    let workload_nas = nas_shares
+        .iter()
+        .map(|x| {
+            let backup = Backup::new(
+                "rt_nas".to_string(),
+                nas_repo_ids[&x.site].0.to_string(),
+                windows.window_for(&x.site, "NAS").id(),
+            );
+
+            let copies = match &x.copy_site {
+                Some(copy_site) => Copy::new(
+                    "rt_nas".to_string(),
+                    nas_repo_ids[copy_site].0.to_string(),
+                    windows.window_for(copy_site, "NAS").id(),
+                ),
+                None => Copy::new("".to_string(), "".to_string(), "".to_string()),
+            };
+
+            WorkloadNas::new(
+                backup,
+                copies,
+                x.files.clamp(0, i32::MAX as i64) as i32,
+                x.copy_site.is_some(),
+                growth.data_property_id(&x.site),
+                site_ids[&x.site].to_string(),
+                Capacity::new(x.size_gb, Unit::Gb).to(VSE_SOURCE_UNIT),
+                ids.id(
+                    &format!("nas:{}|{}", x.site, x.name),
+                    &format!("{}_nas", x.name),
+                ),
+                format!("{}_nas", x.name),
+                false,
+            )
+        })
+        .collect::<Vec<WorkloadNas>>();
+
     Ok(NewVse::new(
-        3,
+        growth.project_length,
         sites,
         repos,
         vec![cap_tier, arch_tier],
-        vec![data_property],
-        vec![window],
-        vec![retention],
+        data_properties,
+        windows.windows(),
+        retentions,
         workloads,
-        vec![],
+        workload_nas,
         "Millions".to_string(),
     ))
 }
+
+/// Reads a VSE file in the v0.11.0 layout, the only one written and read, errors give the
+/// path and line of the value that doesn't fit, e.g.
+/// "workloads[1].sourceTB: invalid type: string "x", expected f64 at line 80 column 21"
+pub fn from_str(text: &str) -> Result<NewVse> {
+    let deserializer = &mut serde_json::Deserializer::from_str(text);
+
+    Ok(serde_path_to_error::deserialize(deserializer)?)
+}
+
+fn perf_repo(repo_id: &str, repo_name: &str, site_id: &str) -> PerfTierRepo {
+    PerfTierRepo::new(
+        repo_id.to_string(),
+        repo_name.to_string(),
+        site_id.to_string(),
+        false,
+        false,
+        false,
+        0,
+        0,
+        "general-s3compatible-capacity".to_string(),
+        "general-glacier-archive".to_string(),
+        "xfsRefs".to_string(),
+        false,
+        false,
+        false,
+    )
+}
+
+fn retention_id(ids: &mut IdGenerator, pattern: &str) -> String {
+    ids.id(
+        &format!("retention:{}", pattern),
+        &format!("rt_{}", pattern),
+    )
+}
+
+/// Checks a retention follows the 30D1W1M1Y pattern
+pub fn validate_retention(pattern: &str) -> Result<()> {
+    parse_retention("", Some(pattern), false).map(|_| ())
+}
+
+/// Builds a retention from the 30D1W1M1Y pattern, 30 days when no pattern is given
+fn parse_retention(id: &str, retention: Option<&str>, default: bool) -> Result<Retentions> {
+    let (simple, weekly, monthly, yearly) = match retention {
+        Some(retention) => {
+            let re = Regex::new(r"^(\d+)D(\d+)W(\d+)M(\d+)Y$").unwrap();
+
+            let caps = re
+                .captures(retention)
+                .ok_or_else(|| anyhow!("Retention must follow the 30D1W1M1Y pattern"))?;
+
+            (
+                caps[1].parse::<i64>()?,
+                caps[2].parse::<i64>()?,
+                caps[3].parse::<i64>()?,
+                caps[4].parse::<i64>()?,
+            )
+        }
+        None => (30, 0, 0, 0),
+    };
+
+    Ok(Retentions::new(
+        id.to_string(),
+        retention.unwrap_or("30D").to_string(),
+        "Instance".to_string(),
+        simple,
+        weekly,
+        monthly,
+        yearly,
+        default,
+    ))
+}
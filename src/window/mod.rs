@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use comfy_table::{
+    modifiers::{UTF8_ROUND_CORNERS, UTF8_SOLID_INNER_BORDERS},
+    presets::UTF8_FULL,
+    Table,
+};
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::{
+    models::{
+        cli::Cli,
+        new_model::{NewVse, Window},
+        units::{Capacity, Unit},
+    },
+    vse::VSE_SOURCE_UNIT,
+};
+
+const SECONDS_PER_HOUR: f64 = 3600.0;
+
+/// Full and incremental backup window lengths in hours
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BackupWindow {
+    pub full: i64,
+    pub incremental: i64,
+}
+
+impl BackupWindow {
+    pub fn id(&self) -> String {
+        format!("bw{}_{}", self.full, self.incremental)
+    }
+
+    pub fn to_vse(self, default: bool) -> Window {
+        Window::new(
+            self.id(),
+            format!("{}h full / {}h incremental", self.full, self.incremental),
+            self.full,
+            self.incremental,
+            default,
+        )
+    }
+}
+
+/// Backup windows resolved from the CLI, a site window overrides a workload class window
+#[derive(Debug, Clone)]
+pub struct WindowPolicy {
+    pub default: BackupWindow,
+    pub sites: HashMap<String, BackupWindow>,
+    pub classes: HashMap<String, BackupWindow>,
+}
+
+impl WindowPolicy {
+    pub fn from_cli(cli: &Cli) -> Result<Self> {
+        Ok(Self {
+            default: parse_window(&cli.backup_window)?,
+            sites: parse_window_map(&cli.site_window)?,
+            classes: parse_window_map(&cli.class_window)?,
+        })
+    }
+
+    pub fn window_for(&self, site: &str, class: &str) -> BackupWindow {
+        self.sites
+            .get(site)
+            .or_else(|| self.classes.get(class))
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// All the windows in use, the global default first
+    pub fn windows(&self) -> Vec<Window> {
+        let mut windows = vec![self.default.to_vse(true)];
+        self.sites
+            .values()
+            .chain(self.classes.values())
+            .filter(|x| **x != self.default)
+            .sorted_by_key(|x| (x.full, x.incremental))
+            .dedup()
+            .for_each(|x| windows.push(x.to_vse(false)));
+        windows
+    }
+}
+
+/// Parses a window in the FULL:INCREMENTAL hours format, e.g. 24:12
+fn parse_window(window: &str) -> Result<BackupWindow> {
+    let (full, incremental) = window.split_once(':').ok_or_else(|| {
+        anyhow!(
+            "Backup window must be FULL:INCREMENTAL hours, got {}",
+            window
+        )
+    })?;
+
+    let window = BackupWindow {
+        full: full.trim().parse()?,
+        incremental: incremental.trim().parse()?,
+    };
+
+    if window.full <= 0 || window.incremental <= 0 {
+        return Err(anyhow!(
+            "Backup window hours must be above 0, got {}",
+            window.id()
+        ));
+    }
+
+    Ok(window)
+}
+
+/// Parses NAME=FULL:INCREMENTAL items
+fn parse_window_map(items: &Option<Vec<String>>) -> Result<HashMap<String, BackupWindow>> {
+    let mut map = HashMap::new();
+
+    if let Some(items) = items {
+        for item in items {
+            let (name, window) = item
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Window must be NAME=FULL:INCREMENTAL, got {}", item))?;
+            map.insert(name.trim().to_string(), parse_window(window)?);
+        }
+    }
+
+    Ok(map)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowCheck {
+    pub site_id: String,
+    pub source_tb: f64,
+    pub full_mbps: f64,
+    pub incremental_mbps: f64,
+    pub exceeded: bool,
+}
+
+/// Throughput (MB/s) needed per site to complete every workload inside its window
+pub fn check(vse: &NewVse, max_throughput: f64) -> Vec<WindowCheck> {
+    let default_dp = vse.data_properties.iter().find(|x| x.default);
+    let default_window = vse.windows.iter().find(|x| x.default);
+
+    vse.workloads
+        .iter()
+        .filter(|x| x.enabled)
+        .sorted_by_key(|x| &x.site_id)
+        .group_by(|x| &x.site_id)
+        .into_iter()
+        .map(|(site_id, group)| {
+            let mut source_tb = 0.0;
+            let mut full_mbps = 0.0;
+            let mut incremental_mbps = 0.0;
+
+            group.for_each(|x| {
+                let change_rate = vse
+                    .data_properties
+                    .iter()
+                    .find(|dp| dp.data_property_id == x.data_property_id)
+                    .or(default_dp)
+                    .map(|dp| dp.change_rate)
+                    .unwrap_or(5);
+
+                let (full, incremental) = vse
+                    .windows
+                    .iter()
+                    .find(|w| w.backup_window_id == x.backup.backup_window_id)
+                    .or(default_window)
+                    .map(|w| (w.full_window, w.incremental_window))
+                    .unwrap_or((24, 12));
+
+                let source_mb = Capacity::new(x.source_tb, VSE_SOURCE_UNIT).to(Unit::Mb);
+
+                source_tb += x.source_tb;
+                full_mbps += source_mb / (full as f64 * SECONDS_PER_HOUR);
+                incremental_mbps += source_mb * change_rate as f64
+                    / 100.0
+                    / (incremental as f64 * SECONDS_PER_HOUR);
+            });
+
+            WindowCheck {
+                site_id: site_id.to_string(),
+                source_tb,
+                full_mbps,
+                incremental_mbps,
+                exceeded: full_mbps > max_throughput || incremental_mbps > max_throughput,
+            }
+        })
+        .collect()
+}
+
+pub fn print_check(checks: &[WindowCheck], max_throughput: f64) {
+    let mut table = Table::new();
+
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(vec![
+            "Site",
+            "Source (TB)",
+            "Full (MB/s)",
+            "Incremental (MB/s)",
+            "Feasible",
+        ]);
+
+    checks.iter().for_each(|x| {
+        table.add_row(vec![
+            x.site_id.to_string(),
+            format!("{:.2}", x.source_tb),
+            format!("{:.0}", x.full_mbps),
+            format!("{:.0}", x.incremental_mbps),
+            if x.exceeded { "No" } else { "Yes" }.to_string(),
+        ]);
+    });
+
+    println!(
+        "Backup window throughput (limit {:.0} MB/s per site)",
+        max_throughput
+    );
+    println!("{table}");
+
+    let exceeded = checks.iter().filter(|x| x.exceeded).collect::<Vec<_>>();
+    if !exceeded.is_empty() {
+        println!(
+            "Sites needing more than {:.0} MB/s: {}",
+            max_throughput,
+            exceeded.iter().map(|x| x.site_id.as_str()).join(", ")
+        );
+    }
+}